@@ -0,0 +1,103 @@
+#![no_main]
+
+use decimal::{Decimal, RoundingMode};
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigInt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+#[path = "../../src/model.rs"]
+mod model;
+
+use model::Model;
+
+// Operands this long are multiplied with Karatsuba's algorithm.
+const MAX_DIGITS: usize = 400;
+const MAX_SCALE: u8 = 60;
+
+const MODES: [RoundingMode; 5] = [
+    RoundingMode::HalfEven,
+    RoundingMode::HalfUp,
+    RoundingMode::Floor,
+    RoundingMode::Ceiling,
+    RoundingMode::Truncate,
+];
+
+// Sign, digits (one per byte) and scale of an operand.
+type Operand = (bool, Vec<u8>, u8);
+
+fn model((negative, bytes, scale): Operand) -> Model {
+    let digits = bytes
+        .iter()
+        .take(MAX_DIGITS)
+        .map(|byte| char::from(b'0' + byte % 10))
+        .collect::<String>();
+    let mantissa = digits.parse::<BigInt>().unwrap_or_default();
+
+    Model::new(
+        if negative { -mantissa } else { mantissa },
+        u32::from(scale % MAX_SCALE),
+    )
+}
+
+fn decimal(model: &Model) -> Decimal {
+    model.to_padded_string(0, 0).parse().unwrap()
+}
+
+fn hash(decimal: &Decimal) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    decimal.hash(&mut hasher);
+    hasher.finish()
+}
+
+fuzz_target!(|input: (Operand, Operand, Operand, u8, u8)| {
+    let (a, b, c) = (model(input.0), model(input.1), model(input.2));
+    let (x, y, z) = (decimal(&a), decimal(&b), decimal(&c));
+    let scale = input.3 % MAX_SCALE;
+    let mode = MODES[usize::from(input.4) % MODES.len()];
+    let zero = Decimal::default();
+
+    // Zero padding changes neither the value nor the hash.
+    let padding = (usize::from(input.3 % 4), usize::from(input.4 % 4));
+    let padded = a
+        .to_padded_string(padding.0, padding.1)
+        .parse::<Decimal>()
+        .unwrap();
+
+    assert_eq!(padded, x);
+    assert_eq!(hash(&padded), hash(&x));
+    assert_eq!(padded.to_string(), a.to_canonical_string());
+
+    assert_eq!((&x + &y).to_string(), a.add(&b).to_canonical_string());
+    assert_eq!((&x - &y).to_string(), a.sub(&b).to_canonical_string());
+    assert_eq!((&x * &y).to_string(), a.mul(&b).to_canonical_string());
+    assert_eq!(x.cmp(&y), a.cmp(&b));
+
+    assert_eq!(&x + &y, &y + &x);
+    assert_eq!(&x * &y, &y * &x);
+    assert_eq!(&(&x + &y) + &z, &x + &(&y + &z));
+    assert_eq!(&(&x * &y) * &z, &x * &(&y * &z));
+    assert_eq!(&(&x - &y) + &y, x);
+    assert_eq!(&x + &-&x, zero);
+
+    let quotient = x.checked_div(&y, usize::from(scale), mode);
+    let expected = a.div(&b, u32::from(scale), mode);
+
+    assert_eq!(
+        quotient.map(|quotient| quotient.to_string()),
+        expected.map(|expected| expected.to_canonical_string())
+    );
+
+    let rounded = x.round(usize::from(scale), mode);
+
+    assert_eq!(
+        rounded.to_string(),
+        a.round(u32::from(scale), mode).to_canonical_string()
+    );
+
+    match x.sqrt(usize::from(scale)) {
+        None => assert!(x < zero),
+        Some(root) => {
+            assert!(a.is_rounded_sqrt(&Model::parse(&root.to_string()), u32::from(scale)))
+        }
+    }
+});
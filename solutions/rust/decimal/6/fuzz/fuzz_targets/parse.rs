@@ -0,0 +1,20 @@
+#![no_main]
+
+use decimal::Decimal;
+use libfuzzer_sys::fuzz_target;
+
+// Any input may be rejected, but never with a panic, and whatever parses
+// must print in canonical form and parse back to the same value.
+fuzz_target!(|input: &str| {
+    let Ok(decimal) = input.parse::<Decimal>() else {
+        return;
+    };
+
+    let printed = decimal.to_string();
+    let reparsed = printed
+        .parse::<Decimal>()
+        .expect("Display output must parse.");
+
+    assert_eq!(reparsed, decimal);
+    assert_eq!(reparsed.to_string(), printed);
+});
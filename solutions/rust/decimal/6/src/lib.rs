@@ -1,282 +1,1411 @@
 use std::cmp::Ordering;
-use std::collections::VecDeque;
-use std::iter;
-use std::ops::{Add, Mul, Sub};
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::{Product, Sum};
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
 
-fn align(lhs: &Decimal, rhs: &Decimal) -> (Vec<u8>, Vec<u8>, usize) {
-    let len_i = lhs.integer().len().max(rhs.integer().len());
-    let len_f = lhs.fractional().len().max(rhs.fractional().len());
+/// Number of fractional digits the `/` operator rounds its result to.
+pub const DIV_SCALE: usize = 20;
 
-    let lhs_i = [&vec![0; len_i - lhs.integer().len()], lhs.integer()].concat();
-    let lhs_f = [lhs.fractional(), &vec![0; len_f - lhs.fractional().len()]].concat();
-    let rhs_i = [&vec![0; len_i - rhs.integer().len()], rhs.integer()].concat();
-    let rhs_f = [rhs.fractional(), &vec![0; len_f - rhs.fractional().len()]].concat();
+// Largest exponent accepted in scientific notation, so that a short input
+// cannot make the parser allocate huge amounts of digits.
+const MAX_EXPONENT: isize = 1_000_000;
 
-    ([lhs_i, lhs_f].concat(), [rhs_i, rhs_f].concat(), len_f)
+// Split off an optional sign, returning whether it was a minus and the number
+// of bytes it took.
+fn strip_sign(string: &str) -> (bool, usize, &str) {
+    match string.as_bytes().first() {
+        Some(b'-') => (true, 1, &string[1..]),
+        Some(b'+') => (false, 1, &string[1..]),
+        _ => (false, 0, string),
+    }
+}
+
+// Parse the exponent of scientific notation, which starts at the given position
+// of the input.
+fn parse_exponent(string: &str, start: usize) -> Result<isize, ParseDecimalError> {
+    let (negative, sign_len, digits) = strip_sign(string);
+
+    if digits.is_empty() {
+        return Err(ParseDecimalError::InvalidExponent);
+    }
+
+    let mut exponent: isize = 0;
+
+    for (index, c) in digits.char_indices() {
+        let position = start + sign_len + index;
+        let digit = c
+            .to_digit(10)
+            .ok_or(ParseDecimalError::InvalidChar { position, found: c })?;
+
+        exponent = exponent * 10 + digit as isize;
+
+        if exponent > MAX_EXPONENT {
+            return Err(ParseDecimalError::ExponentOutOfRange);
+        }
+    }
+
+    Ok(if negative { -exponent } else { exponent })
+}
+
+// Limbs hold base 10^9 digits, least significant first, without most
+// significant zero limbs, so zero has no limbs at all.
+const BASE: u64 = 1_000_000_000;
+const LIMB_DIGITS: usize = 9;
+
+// Operands shorter than this many limbs are multiplied by long multiplication,
+// longer ones with Karatsuba's algorithm.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+
+    limbs
+}
+
+fn limbs_from_digits(digits: &[u8]) -> Vec<u32> {
+    trim(
+        digits
+            .rchunks(LIMB_DIGITS)
+            .map(|chunk| chunk.iter().fold(0, |limb, d| limb * 10 + *d as u32))
+            .collect(),
+    )
+}
+
+fn limbs_from_u128(mut value: u128) -> Vec<u32> {
+    let mut limbs = vec![];
+
+    while value > 0 {
+        limbs.push((value % BASE as u128) as u32);
+        value /= BASE as u128;
+    }
+
+    limbs
+}
+
+fn limbs_to_digits(limbs: &[u32]) -> String {
+    let Some((last, rest)) = limbs.split_last() else {
+        return "0".to_owned();
+    };
+
+    let mut digits = last.to_string();
+
+    for limb in rest.iter().rev() {
+        digits.push_str(&format!("{limb:09}"));
+    }
+
+    digits
+}
+
+fn cmp_limbs(lhs: &[u32], rhs: &[u32]) -> Ordering {
+    lhs.len()
+        .cmp(&rhs.len())
+        .then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_limbs(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(lhs.len().max(rhs.len()) + 1);
+    let mut carry = 0;
+
+    for idx in 0..lhs.len().max(rhs.len()) {
+        let sum = *lhs.get(idx).unwrap_or(&0) as u64 + *rhs.get(idx).unwrap_or(&0) as u64 + carry;
+        result.push((sum % BASE) as u32);
+        carry = sum / BASE;
+    }
+
+    result.push(carry as u32);
+    trim(result)
+}
+
+// The left-hand side must not be less than the right-hand side.
+fn sub_limbs(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(lhs.len());
+    let mut borrow = 0;
+
+    for (idx, l) in lhs.iter().enumerate() {
+        let mut difference = *l as i64 - *rhs.get(idx).unwrap_or(&0) as i64 - borrow;
+        borrow = (difference < 0) as i64;
+        difference += borrow * BASE as i64;
+        result.push(difference as u32);
+    }
+
+    trim(result)
+}
+
+fn mul_small(limbs: &[u32], factor: u32) -> Vec<u32> {
+    let mut result = Vec::with_capacity(limbs.len() + 1);
+    let mut carry = 0;
+
+    for limb in limbs {
+        let product = *limb as u64 * factor as u64 + carry;
+        result.push((product % BASE) as u32);
+        carry = product / BASE;
+    }
+
+    result.push(carry as u32);
+    trim(result)
+}
+
+fn mul_pow10(limbs: &[u32], exponent: usize) -> Vec<u32> {
+    if limbs.is_empty() {
+        return vec![];
+    }
+
+    let shifted = mul_small(limbs, 10u32.pow((exponent % LIMB_DIGITS) as u32));
+
+    [vec![0; exponent / LIMB_DIGITS], shifted].concat()
+}
+
+fn pow10(exponent: usize) -> Vec<u32> {
+    mul_pow10(&[1], exponent)
+}
+
+fn mul_limbs(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    if lhs.len().min(rhs.len()) < KARATSUBA_THRESHOLD {
+        return mul_long(lhs, rhs);
+    }
+
+    // Split both operands at the same limb, x = x1 * B + x0, so that
+    // x * y = z2 * B^2 + z1 * B + z0, where z2 = x1 * y1, z0 = x0 * y0
+    // and z1 = (x0 + x1) * (y0 + y1) - z2 - z0 takes a single multiplication.
+    let half = lhs.len().max(rhs.len()) / 2;
+    let (lhs_low, lhs_high) = split_limbs(lhs, half);
+    let (rhs_low, rhs_high) = split_limbs(rhs, half);
+
+    let z0 = mul_limbs(&lhs_low, &rhs_low);
+    let z2 = mul_limbs(&lhs_high, &rhs_high);
+    let sums = mul_limbs(
+        &add_limbs(&lhs_low, &lhs_high),
+        &add_limbs(&rhs_low, &rhs_high),
+    );
+    let z1 = sub_limbs(&sub_limbs(&sums, &z0), &z2);
+
+    let mut result = vec![0; lhs.len() + rhs.len()];
+    add_shifted(&mut result, &z0, 0);
+    add_shifted(&mut result, &z1, half);
+    add_shifted(&mut result, &z2, 2 * half);
+
+    trim(result)
+}
+
+fn mul_long(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = vec![0; lhs.len() + rhs.len()];
+
+    for (i, l) in lhs.iter().enumerate() {
+        let mut carry = 0;
+
+        for (j, r) in rhs.iter().enumerate() {
+            let product = *l as u64 * *r as u64 + result[i + j] as u64 + carry;
+            result[i + j] = (product % BASE) as u32;
+            carry = product / BASE;
+        }
+
+        result[i + rhs.len()] = carry as u32;
+    }
+
+    trim(result)
+}
+
+fn split_limbs(limbs: &[u32], at: usize) -> (Vec<u32>, Vec<u32>) {
+    let (low, high) = limbs.split_at(at.min(limbs.len()));
+
+    (trim(low.to_vec()), high.to_vec())
+}
+
+// Add limbs shifted by the given number of limbs to the result, which must
+// be long enough to hold the sum.
+fn add_shifted(result: &mut [u32], limbs: &[u32], shift: usize) {
+    let mut carry = 0;
+    let mut idx = shift;
+
+    for limb in limbs {
+        let sum = result[idx] as u64 + *limb as u64 + carry;
+        result[idx] = (sum % BASE) as u32;
+        carry = sum / BASE;
+        idx += 1;
+    }
+
+    while carry > 0 {
+        let sum = result[idx] as u64 + carry;
+        result[idx] = (sum % BASE) as u32;
+        carry = sum / BASE;
+        idx += 1;
+    }
+}
+
+fn div_small(limbs: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0; limbs.len()];
+    let mut remainder = 0;
+
+    for idx in (0..limbs.len()).rev() {
+        let current = remainder * BASE + limbs[idx] as u64;
+        quotient[idx] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+
+    (trim(quotient), remainder as u32)
+}
+
+// Long division, returning the quotient and the remainder. The divisor must
+// not be zero.
+fn div_limbs(lhs: &[u32], rhs: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = rhs {
+        let (quotient, remainder) = div_small(lhs, *divisor);
+        return (quotient, trim(vec![remainder]));
+    }
+
+    let mut quotient = vec![0; lhs.len()];
+    let mut remainder = vec![];
+
+    // Each quotient limb is estimated from the two most significant limbs
+    // of the divisor, which narrows it down to a couple of candidates.
+    let low = rhs.len() - 2;
+    let top = rhs[low + 1] as u128 * BASE as u128 + rhs[low] as u128;
+
+    for idx in (0..lhs.len()).rev() {
+        remainder.insert(0, lhs[idx]);
+        remainder = trim(remainder);
+
+        if cmp_limbs(&remainder, rhs).is_lt() {
+            continue;
+        }
+
+        let estimate = remainder[low..]
+            .iter()
+            .rev()
+            .fold(0, |value, limb| value * BASE as u128 + *limb as u128);
+        let mut min = estimate / (top + 1);
+        let mut max = (estimate / top).min(BASE as u128 - 1);
+
+        while min < max {
+            let mid = (min + max).div_ceil(2);
+
+            if cmp_limbs(&mul_small(rhs, mid as u32), &remainder).is_le() {
+                min = mid;
+            } else {
+                max = mid - 1;
+            }
+        }
+
+        quotient[idx] = min as u32;
+        remainder = sub_limbs(&remainder, &mul_small(rhs, min as u32));
+    }
+
+    (trim(quotient), remainder)
 }
 
-fn num_string_to_vec(string: &str) -> Vec<u8> {
-    string
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as u8)
-        .collect()
+// Divide magnitudes, rounding the quotient to an integer.
+fn div_rounded(negative: bool, lhs: &[u32], rhs: &[u32], mode: RoundingMode) -> Vec<u32> {
+    let (quotient, remainder) = div_limbs(lhs, rhs);
+
+    // Compare the remainder with a half of the divisor.
+    let discarded = match remainder.is_empty() {
+        true => None,
+        false => Some(cmp_limbs(&remainder, &sub_limbs(rhs, &remainder))),
+    };
+
+    let odd = quotient.first().is_some_and(|limb| limb % 2 == 1);
+
+    if mode.rounds_up(negative, odd, discarded) {
+        add_limbs(&quotient, &[1])
+    } else {
+        quotient
+    }
 }
 
-#[derive(Debug)]
+// Integer square root, rounded down, by Newton's iteration.
+fn sqrt_limbs(limbs: &[u32]) -> Vec<u32> {
+    if limbs.is_empty() {
+        return vec![];
+    }
+
+    // Start above the root, from where the iteration decreases monotonically
+    // until it reaches it.
+    let digits = limbs_to_digits(limbs).len();
+    let mut root = pow10(digits.div_ceil(2));
+
+    loop {
+        let (quotient, _) = div_limbs(limbs, &root);
+        let (next, _) = div_small(&add_limbs(&root, &quotient), 2);
+
+        if cmp_limbs(&next, &root).is_ge() {
+            return root;
+        }
+
+        root = next;
+    }
+}
+
+/// How to round a value that does not fit into the requested scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Round to the nearest value, ties to the even neighbour.
+    #[default]
+    HalfEven,
+    /// Round to the nearest value, ties away from zero.
+    HalfUp,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards positive infinity.
+    Ceiling,
+    /// Round towards zero.
+    Truncate,
+}
+
+impl RoundingMode {
+    // Whether a truncated magnitude has to be rounded away from zero, given
+    // how the discarded part compares to half a unit in the last place
+    // (`None` if nothing was discarded).
+    fn rounds_up(self, negative: bool, odd: bool, discarded: Option<Ordering>) -> bool {
+        let Some(discarded) = discarded else {
+            return false;
+        };
+
+        match self {
+            RoundingMode::HalfEven => discarded.is_gt() || (discarded.is_eq() && odd),
+            RoundingMode::HalfUp => discarded.is_ge(),
+            RoundingMode::Floor => negative,
+            RoundingMode::Ceiling => !negative,
+            RoundingMode::Truncate => false,
+        }
+    }
+}
+
+/// An error returned when parsing a decimal fails. Positions are byte
+/// offsets into the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDecimalError {
+    /// The input contains no digits.
+    Empty,
+    /// A character that does not belong to a decimal number.
+    InvalidChar { position: usize, found: char },
+    /// A second decimal point.
+    MultiplePoints { position: usize },
+    /// An exponent marker not followed by digits.
+    InvalidExponent,
+    /// An exponent too large in magnitude.
+    ExponentOutOfRange,
+}
+
+impl Display for ParseDecimalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDecimalError::Empty => f.write_str("cannot parse decimal from empty string"),
+            ParseDecimalError::InvalidChar { position, found } => {
+                write!(f, "invalid character {found:?} at position {position}")
+            }
+            ParseDecimalError::MultiplePoints { position } => {
+                write!(f, "second decimal point at position {position}")
+            }
+            ParseDecimalError::InvalidExponent => f.write_str("exponent has no digits"),
+            ParseDecimalError::ExponentOutOfRange => f.write_str("exponent out of range"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+/// How to convert a float to a decimal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatConversion {
+    /// The exact value of the float, e.g. `0.1` becomes
+    /// `0.1000000000000000055511151231257827021181583404541015625`.
+    Exact,
+    /// The shortest decimal that converts back to the same float,
+    /// e.g. `0.1` becomes `0.1`.
+    #[default]
+    Shortest,
+}
+
+/// An error returned when converting between decimals and other numbers fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The float is infinite or NaN.
+    NonFinite,
+    /// The value is out of range of the target type.
+    Overflow,
+    /// The value cannot be represented exactly by the target type.
+    PrecisionLoss,
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::NonFinite => f.write_str("cannot convert a non-finite float"),
+            ConversionError::Overflow => f.write_str("value out of range"),
+            ConversionError::PrecisionLoss => f.write_str("value cannot be represented exactly"),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Options for `Decimal::format`, e.g. `1,234.50` with
+/// `Format::new().with_grouping(',').with_scale(2)`, or `1.234,50` with
+/// `.with_decimal_mark(',').with_grouping('.')` on top of that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    decimal_mark: char,
+    group_separator: Option<char>,
+    scale: Option<usize>,
+    rounding: RoundingMode,
+}
+
+impl Format {
+    /// Plain format with a `.` decimal mark, no grouping and no trailing zeros.
+    pub fn new() -> Self {
+        Format {
+            decimal_mark: '.',
+            group_separator: None,
+            scale: None,
+            rounding: RoundingMode::default(),
+        }
+    }
+
+    pub fn with_decimal_mark(mut self, mark: char) -> Self {
+        self.decimal_mark = mark;
+        self
+    }
+
+    /// Separate groups of three integer digits.
+    pub fn with_grouping(mut self, separator: char) -> Self {
+        self.group_separator = Some(separator);
+        self
+    }
+
+    /// Print exactly this many fractional digits, rounding or padding
+    /// with zeros as needed.
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    /// Set how values are rounded to the fixed scale, half-even by default.
+    pub fn with_rounding(mut self, rounding: RoundingMode) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    fn group(&self, digits: &str) -> String {
+        let Some(separator) = self.group_separator else {
+            return digits.to_owned();
+        };
+
+        let mut result = String::with_capacity(digits.len() * 4 / 3);
+
+        for (idx, c) in digits.chars().enumerate() {
+            if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+                result.push(separator);
+            }
+
+            result.push(c);
+        }
+
+        result
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::new()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Decimal {
     negative: bool,
-    decimal_idx: usize,
-    digits: Vec<u8>
+    // Number of digits after the decimal point.
+    scale: usize,
+    // Digits of the number without the decimal point.
+    limbs: Vec<u32>,
 }
 
 impl Decimal {
     pub fn try_from(input: &str) -> Option<Decimal> {
-        let mut negative = true;
-
-        let input = input.strip_prefix('-').unwrap_or_else(|| {
-            negative = false;
-            input.strip_prefix('+').unwrap_or(input)
-        });
-
-        input
-            .find('.')
-            .map(|index| Decimal {
-                negative,
-                decimal_idx: index,
-                digits: [
-                    num_string_to_vec(&input[..index]),
-                    num_string_to_vec(&input[index + 1..])
-                ].concat(),
-            })
-            .or_else(|| {
-                Some(Decimal {
-                    negative,
-                    decimal_idx: input.len(),
-                    digits: [num_string_to_vec(input), vec![0]].concat(),
-                })
-            })
+        input.parse().ok()
     }
 
-    fn is_zero(&self) -> bool {
-        self.digits.iter().all(|d| *d == 0)
+    /// Remove trailing zeros of the fractional part.
+    pub fn normalize(&self) -> Decimal {
+        let mut limbs = self.limbs.clone();
+        let mut scale = if limbs.is_empty() { 0 } else { self.scale };
+
+        // Drop whole zero limbs first, then single zero digits.
+        while scale >= LIMB_DIGITS && limbs[0] == 0 {
+            limbs.remove(0);
+            scale -= LIMB_DIGITS;
+        }
+
+        while scale > 0 && limbs[0].is_multiple_of(10) {
+            limbs = div_small(&limbs, 10).0;
+            scale -= 1;
+        }
+
+        Decimal::from_parts(self.negative, limbs, scale)
     }
 
-    fn abs(&self) -> Decimal {
+    fn from_parts(negative: bool, limbs: Vec<u32>, scale: usize) -> Decimal {
+        let limbs = trim(limbs);
+
         Decimal {
-            negative: false,
-            decimal_idx: self.decimal_idx,
-            digits: self.digits.clone()
+            // Zero is never negative.
+            negative: negative && !limbs.is_empty(),
+            scale,
+            limbs,
+        }
+    }
+
+    /// Round to the given number of fractional digits.
+    pub fn round(&self, scale: usize, mode: RoundingMode) -> Decimal {
+        if self.scale <= scale {
+            return Decimal::from_parts(self.negative, self.limbs.clone(), self.scale);
+        }
+
+        let divisor = pow10(self.scale - scale);
+        let limbs = div_rounded(self.negative, &self.limbs, &divisor, mode);
+
+        Decimal::from_parts(self.negative, limbs, scale)
+    }
+
+    /// Divide, rounding the quotient to the given number of fractional digits.
+    /// Returns `None` when dividing by zero.
+    pub fn checked_div(&self, rhs: &Decimal, scale: usize, mode: RoundingMode) -> Option<Decimal> {
+        if rhs.is_zero() {
+            return None;
+        }
+
+        let negative = self.negative != rhs.negative;
+
+        // Scale the operands so that dividing them as integers yields the
+        // quotient with the requested number of fractional digits.
+        let shift = (scale + rhs.scale) as isize - self.scale as isize;
+
+        let (lhs, divisor) = if shift >= 0 {
+            (mul_pow10(&self.limbs, shift as usize), rhs.limbs.clone())
+        } else {
+            (
+                self.limbs.clone(),
+                mul_pow10(&rhs.limbs, shift.unsigned_abs()),
+            )
+        };
+
+        let limbs = div_rounded(negative, &lhs, &divisor, mode);
+
+        Some(Decimal::from_parts(negative, limbs, scale))
+    }
+
+    /// The value without its sign.
+    pub fn abs(&self) -> Decimal {
+        Decimal::from_parts(false, self.limbs.clone(), self.scale)
+    }
+
+    /// `-1`, `0` or `1` depending on the sign.
+    pub fn signum(&self) -> Decimal {
+        match (self.is_zero(), self.negative) {
+            (true, _) => Decimal::default(),
+            (false, true) => Decimal::from(-1i64),
+            (false, false) => Decimal::from(1i64),
+        }
+    }
+
+    /// Raise to an integer power by repeated squaring. The result is exact,
+    /// so its scale is the scale of the decimal times the exponent.
+    pub fn pow(&self, mut exponent: u32) -> Decimal {
+        let mut result = Decimal::from(1i64);
+        let mut base = self.clone();
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result *= &base;
+            }
+
+            exponent >>= 1;
+
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /// Square root rounded to the nearest value with the given number of
+    /// fractional digits, or `None` for negative decimals.
+    pub fn sqrt(&self, precision: usize) -> Option<Decimal> {
+        if self.negative {
+            return None;
+        }
+
+        // The rounded root of x is floor(sqrt(x) + 1/2), which equals
+        // (floor(sqrt(4x)) + 1) / 2. Taking the floor of 4x first does not
+        // change the integer root.
+        let shift = 2 * precision as isize - self.scale as isize;
+        let quadruple = mul_small(&self.limbs, 4);
+
+        let radicand = if shift >= 0 {
+            mul_pow10(&quadruple, shift as usize)
+        } else {
+            div_limbs(&quadruple, &pow10(shift.unsigned_abs())).0
+        };
+
+        let (root, _) = div_small(&add_limbs(&sqrt_limbs(&radicand), &[1]), 2);
+
+        Some(Decimal::from_parts(false, root, precision))
+    }
+
+    /// Round towards negative infinity to an integer.
+    pub fn floor(&self) -> Decimal {
+        self.round(0, RoundingMode::Floor)
+    }
+
+    /// Round towards positive infinity to an integer.
+    pub fn ceil(&self) -> Decimal {
+        self.round(0, RoundingMode::Ceiling)
+    }
+
+    /// Round towards zero to an integer.
+    pub fn trunc(&self) -> Decimal {
+        self.round(0, RoundingMode::Truncate)
+    }
+
+    /// The fractional part, with the sign of the decimal.
+    pub fn fract(&self) -> Decimal {
+        self - &self.trunc()
+    }
+
+    /// Convert a float, see `FloatConversion` for the available modes.
+    pub fn from_f64(value: f64, conversion: FloatConversion) -> Result<Decimal, ConversionError> {
+        if !value.is_finite() {
+            return Err(ConversionError::NonFinite);
+        }
+
+        // Floats are displayed with the shortest digits that round-trip,
+        // and without an exponent.
+        if conversion == FloatConversion::Shortest {
+            return Ok(value
+                .to_string()
+                .parse()
+                .expect("Should be a valid decimal number."));
+        }
+
+        let bits = value.to_bits();
+        let biased_exponent = ((bits >> 52) & 0x7ff) as i32;
+        let fraction = bits & ((1 << 52) - 1);
+
+        // The value is mantissa * 2^exponent.
+        let (mantissa, exponent) = match biased_exponent {
+            0 => (fraction, -1074),
+            _ => (fraction | 1 << 52, biased_exponent - 1075),
+        };
+
+        // Dividing by 2^k is the same as multiplying by 5^k and moving
+        // the decimal point k digits to the left.
+        let factor = if exponent < 0 { 5 } else { 2 };
+        let mut limbs = limbs_from_u128(mantissa as u128);
+
+        for _ in 0..exponent.unsigned_abs() {
+            limbs = mul_small(&limbs, factor);
+        }
+
+        let scale = if exponent < 0 {
+            exponent.unsigned_abs() as usize
+        } else {
+            0
+        };
+
+        Ok(Decimal::from_parts(bits >> 63 == 1, limbs, scale))
+    }
+
+    /// Convert to an integer, failing if there is a fractional part
+    /// or the value does not fit.
+    pub fn to_i64(&self) -> Result<i64, ConversionError> {
+        let decimal = self.normalize();
+
+        if decimal.scale > 0 {
+            return Err(ConversionError::PrecisionLoss);
+        }
+
+        let magnitude = decimal
+            .limbs
+            .iter()
+            .rev()
+            .try_fold(0i128, |value, limb| {
+                value.checked_mul(BASE as i128)?.checked_add(*limb as i128)
+            })
+            .ok_or(ConversionError::Overflow)?;
+
+        let value = if decimal.negative {
+            -magnitude
+        } else {
+            magnitude
+        };
+
+        i64::try_from(value).map_err(|_| ConversionError::Overflow)
+    }
+
+    /// Convert to a float, failing if it is not exactly equal to the decimal.
+    pub fn to_f64(&self) -> Result<f64, ConversionError> {
+        let value = self.to_f64_lossy();
+
+        if value.is_infinite() {
+            return Err(ConversionError::Overflow);
+        }
+
+        if Decimal::from_f64(value, FloatConversion::Exact)? != *self {
+            return Err(ConversionError::PrecisionLoss);
+        }
+
+        Ok(value)
+    }
+
+    /// Convert to the nearest float, which is infinite if the decimal is out of range.
+    pub fn to_f64_lossy(&self) -> f64 {
+        self.to_string().parse().expect("Should be a valid float.")
+    }
+
+    /// Format with grouping separators, a fixed scale or another decimal mark.
+    pub fn format(&self, format: &Format) -> String {
+        let decimal = match format.scale {
+            Some(scale) => {
+                let rounded = self.round(scale, format.rounding);
+                let limbs = mul_pow10(&rounded.limbs, scale - rounded.scale);
+
+                Decimal::from_parts(rounded.negative, limbs, scale)
+            }
+            None => self.normalize(),
+        };
+
+        let mut result = String::new();
+
+        if decimal.negative {
+            result.push('-');
         }
+
+        result.push_str(&format.group(&decimal.integer()));
+
+        if decimal.scale > 0 {
+            result.push(format.decimal_mark);
+            result.push_str(&decimal.fractional());
+        }
+
+        result
+    }
+
+    fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    // Magnitudes of both decimals brought to the same scale.
+    fn align(&self, other: &Decimal) -> (Vec<u32>, Vec<u32>, usize) {
+        let scale = self.scale.max(other.scale);
+
+        (
+            mul_pow10(&self.limbs, scale - self.scale),
+            mul_pow10(&other.limbs, scale - other.scale),
+            scale,
+        )
+    }
+
+    // All digits, with at least one of them before the decimal point.
+    fn digits(&self) -> String {
+        let digits = limbs_to_digits(&self.limbs);
+        let padding = (self.scale + 1).saturating_sub(digits.len());
+
+        "0".repeat(padding) + &digits
     }
 
-    fn integer(&self) -> &[u8] {
-        &self.digits[..self.decimal_idx]
+    fn integer(&self) -> String {
+        let digits = self.digits();
+        digits[..digits.len() - self.scale].to_owned()
     }
 
-    fn fractional(&self) -> &[u8] {
-        &self.digits[self.decimal_idx..]
+    fn fractional(&self) -> String {
+        let digits = self.digits();
+        digits[digits.len() - self.scale..].to_owned()
     }
 }
 
-impl Default for Decimal {
-    fn default() -> Self {
-        Decimal {
-            negative: false,
-            decimal_idx: 1,
-            digits: vec![0, 0]
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let decimal = self.normalize();
+
+        if decimal.negative {
+            f.write_str("-")?;
+        }
+
+        f.write_str(&decimal.integer())?;
+
+        if decimal.scale > 0 {
+            write!(f, ".{}", decimal.fractional())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    // Accepts an optional sign, digits with an optional decimal point and
+    // an optional exponent, e.g. `-12.5`, `.5`, `3.` or `1.5e-3`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, sign_len, input) = strip_sign(s);
+
+        let (mantissa, exponent) = match input.find(['e', 'E']) {
+            Some(index) => (&input[..index], Some(index)),
+            None => (input, None),
+        };
+
+        let mut integer = vec![];
+        let mut fractional = vec![];
+        let mut point = false;
+
+        for (index, c) in mantissa.char_indices() {
+            let position = sign_len + index;
+
+            match c {
+                '.' if point => return Err(ParseDecimalError::MultiplePoints { position }),
+                '.' => point = true,
+                c => {
+                    let digit = c
+                        .to_digit(10)
+                        .ok_or(ParseDecimalError::InvalidChar { position, found: c })?;
+
+                    if point {
+                        fractional.push(digit as u8)
+                    } else {
+                        integer.push(digit as u8)
+                    }
+                }
+            }
+        }
+
+        if integer.is_empty() && fractional.is_empty() {
+            return Err(match exponent {
+                Some(index) => ParseDecimalError::InvalidChar {
+                    position: sign_len + index,
+                    found: input[index..].chars().next().unwrap(),
+                },
+                None => ParseDecimalError::Empty,
+            });
         }
+
+        let exponent = match exponent {
+            Some(index) => parse_exponent(&input[index + 1..], sign_len + index + 1)?,
+            None => 0,
+        };
+
+        // Move the decimal point by the exponent, padding with zeros
+        // if it ends up past the last digit.
+        let scale = fractional.len() as isize - exponent;
+        let limbs = limbs_from_digits(&[integer, fractional].concat());
+        let limbs = mul_pow10(&limbs, (-scale).max(0) as usize);
+
+        Ok(Decimal::from_parts(negative, limbs, scale.max(0) as usize))
+    }
+}
+
+impl From<i128> for Decimal {
+    fn from(value: i128) -> Self {
+        Decimal::from_parts(value < 0, limbs_from_u128(value.unsigned_abs()), 0)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal::from(value as i128)
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(value: u64) -> Self {
+        Decimal::from(value as i128)
+    }
+}
+
+// Converts to the shortest decimal that converts back to the same float.
+// Note that `Decimal::try_from` parses strings, this is available as
+// `f64::try_into` or `Decimal::from_f64`.
+impl TryFrom<f64> for Decimal {
+    type Error = ConversionError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Decimal::from_f64(value, FloatConversion::Shortest)
     }
 }
 
 impl PartialEq for Decimal {
     fn eq(&self, other: &Self) -> bool {
-        self.partial_cmp(other).is_some_and(|v| v.is_eq())
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Decimal {}
+
+// Equal values hash the same no matter how many zeros they are padded with.
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let decimal = self.normalize();
+
+        (decimal.negative, decimal.scale, decimal.limbs).hash(state)
     }
 }
 
 impl PartialOrd for Decimal {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        // 1. If both decimals are zero (no matter the sign), they are equal.
-        if self.is_zero() && other.is_zero() {
-            return Some(Ordering::Equal);
-        }
-
-        // 2. A negative decimal is always less than a positive.
-        if self.negative && !other.negative {
-            return Some(Ordering::Less);
-        }
+        Some(self.cmp(other))
+    }
+}
 
-        if !self.negative && other.negative {
-            return Some(Ordering::Greater);
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // A negative decimal is always less than a positive one or zero.
+        match (self.negative, other.negative) {
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            _ => (),
         }
 
-        let (lhs, rhs, _) = align(self, other);
+        let (lhs, rhs, _) = self.align(other);
+        let ordering = cmp_limbs(&lhs, &rhs);
 
-        lhs.partial_cmp(&rhs).map(|o| {
-            if self.negative && other.negative { o.reverse() } else { o }
-        })
+        if self.negative {
+            ordering.reverse()
+        } else {
+            ordering
+        }
     }
 }
 
-impl Add for Decimal {
-    type Output = Decimal;
+// Implement an operator for owned operands and its assigning version in terms
+// of the implementation for borrowed operands.
+macro_rules! forward_op {
+    ($op:ident, $method:ident, $op_assign:ident, $method_assign:ident) => {
+        impl $op for Decimal {
+            type Output = Decimal;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        // Adding two zeroes results in a zero.
-        if self.is_zero() && rhs.is_zero() {
-            return Decimal::default();
-        }
-
-        let mut negative = false;
-
-        // If both decimals are negative, the result is negative too.
-        if self.negative && rhs.negative {
-            negative = true
-        // If only one of the decimals is negative:
-        // 1. Find the larger absolute decimal.
-        // 2. Perform subtraction of the absolutes.
-        // 3. Assign the sign of the larger one to the result.
-        } else if self.negative || rhs.negative {
-            let self_abs = self.abs();
-            let rhs_abs = rhs.abs();
-
-            return if self_abs > rhs_abs {
-                let mut result = self_abs - rhs_abs;
-                result.negative = self.negative;
-                result
-            } else {
-                let mut result = rhs_abs - self_abs;
-                result.negative = rhs.negative;
-                result
-            };
+            fn $method(self, rhs: Decimal) -> Self::Output {
+                (&self).$method(&rhs)
+            }
         }
 
-        let (lhs, rhs, len_f) = align(&self, &rhs);
+        impl $op<&Decimal> for Decimal {
+            type Output = Decimal;
 
-        // Add decimals together digit by digit, starting from the right.
-        // Basically performing column addition.
-        let mut carry = 0;
-        let mut results = VecDeque::new();
+            fn $method(self, rhs: &Decimal) -> Self::Output {
+                (&self).$method(rhs)
+            }
+        }
 
-        for (idx, (l, r)) in iter::zip(lhs.iter().rev(), rhs.iter().rev()).enumerate() {
-            let mut result = l + r + carry;
-            carry = result / 10;
-            result %= 10;
+        impl $op<Decimal> for &Decimal {
+            type Output = Decimal;
 
-            if idx == len_f {
-                results.push_front('.')
+            fn $method(self, rhs: Decimal) -> Self::Output {
+                self.$method(&rhs)
             }
+        }
 
-            results.push_front(char::from_digit(result as u32, 10).unwrap())
+        impl $op_assign for Decimal {
+            fn $method_assign(&mut self, rhs: Decimal) {
+                *self = (&*self).$method(&rhs);
+            }
         }
 
-        Decimal::try_from(&format!(
-            "{}{}",
-            if negative { "-" } else { "" },
-            results.into_iter().collect::<String>()
-        ))
-        .expect("Should be a valid decimal number.")
-    }
+        impl $op_assign<&Decimal> for Decimal {
+            fn $method_assign(&mut self, rhs: &Decimal) {
+                *self = (&*self).$method(rhs);
+            }
+        }
+    };
 }
 
-impl Sub for Decimal {
+forward_op!(Add, add, AddAssign, add_assign);
+forward_op!(Sub, sub, SubAssign, sub_assign);
+forward_op!(Mul, mul, MulAssign, mul_assign);
+forward_op!(Div, div, DivAssign, div_assign);
+
+impl Add for &Decimal {
     type Output = Decimal;
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        // Subtracting two zeroes results in a zero.
-        if self.is_zero() && rhs.is_zero() {
-            return Decimal::default();
-        }
-
-        let mut negative = false;
-
-        // If both decimals are negative, the result is negative too.
-        if self.negative && rhs.negative {
-            negative = true;
-        // If only one of the decimals is negative:
-        // 1. Perform addition of the decimals' absolutes.
-        // 2. Assign the sign of the left-hand side one to the result.
-        } else if self.negative || rhs.negative {
-            let mut result = self.abs() + rhs.abs();
-            result.negative = self.negative;
-            return result;
-            // If the right-hand side is bigger than the left-hand side, reverse the operation.
-        } else if rhs > self {
-            let mut result = rhs - self;
-            result.negative = true;
-            return result;
-        }
-
-        let (lhs, rhs, len_f) = align(&self, &rhs);
-
-        // Subtract decimals digit by digit, starting from the right.
-        // Basically performing column subtraction.
-        let mut carry = 0;
-        let mut results = VecDeque::new();
+    fn add(self, rhs: Self) -> Self::Output {
+        let (lhs_limbs, rhs_limbs, scale) = self.align(rhs);
 
-        for (idx, (l, r)) in iter::zip(lhs.iter().rev(), rhs.iter().rev()).enumerate() {
-            let mut result = *l as i8 - *r as i8 + carry;
-            carry = result.div_euclid(10);
-            result = result + 10 * ((result < 0) as i8);
+        // If both decimals have the same sign, add their absolutes and keep the sign.
+        if self.negative == rhs.negative {
+            return Decimal::from_parts(self.negative, add_limbs(&lhs_limbs, &rhs_limbs), scale);
+        }
 
-            if idx == len_f {
-                results.push_front('.')
+        // Otherwise subtract the smaller absolute from the larger one and
+        // assign the sign of the larger one to the result.
+        match cmp_limbs(&lhs_limbs, &rhs_limbs) {
+            Ordering::Less => {
+                Decimal::from_parts(rhs.negative, sub_limbs(&rhs_limbs, &lhs_limbs), scale)
             }
-
-            results.push_front(char::from_digit(result as u32, 10).unwrap())
+            _ => Decimal::from_parts(self.negative, sub_limbs(&lhs_limbs, &rhs_limbs), scale),
         }
+    }
+}
 
-        Decimal::try_from(&format!(
-            "{}{}",
-            if negative { "-" } else { "" },
-            results.into_iter().collect::<String>()
-        ))
-        .expect("Should be a valid decimal number.")
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + &-rhs
     }
 }
 
-impl Mul for Decimal {
+impl Mul for &Decimal {
     type Output = Decimal;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        // Multiplying two zeroes results in a zero.
-        if self.is_zero() && rhs.is_zero() {
-            return Decimal::default();
+        let limbs = mul_limbs(&self.limbs, &rhs.limbs);
+
+        Decimal::from_parts(self.negative != rhs.negative, limbs, self.scale + rhs.scale)
+    }
+}
+
+impl Div for &Decimal {
+    type Output = Decimal;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs, DIV_SCALE, RoundingMode::HalfEven)
+            .expect("Division by zero.")
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Self::Output {
+        Decimal::from_parts(!self.negative, self.limbs, self.scale)
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Self::Output {
+        -self.clone()
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Self {
+        iter.fold(Decimal::default(), |sum, value| sum + value)
+    }
+}
+
+impl<'a> Sum<&'a Decimal> for Decimal {
+    fn sum<I: Iterator<Item = &'a Decimal>>(iter: I) -> Self {
+        iter.fold(Decimal::default(), |sum, value| sum + value)
+    }
+}
+
+impl Product for Decimal {
+    fn product<I: Iterator<Item = Decimal>>(iter: I) -> Self {
+        iter.fold(Decimal::from(1i64), |product, value| product * value)
+    }
+}
+
+impl<'a> Product<&'a Decimal> for Decimal {
+    fn product<I: Iterator<Item = &'a Decimal>>(iter: I) -> Self {
+        iter.fold(Decimal::from(1i64), |product, value| product * value)
+    }
+}
+
+#[cfg(test)]
+mod model;
+
+#[cfg(feature = "serde")]
+pub mod serde {
+    //! Serde support, enabled by the `serde` feature. Decimals are serialized
+    //! as strings so that no precision is lost. See `number` for serializing
+    //! them as numbers instead.
+
+    use super::Decimal;
+    use ::serde::de::{self, Deserializer, Visitor};
+    use ::serde::{Deserialize, Serialize, Serializer};
+    use std::fmt::{self, Formatter};
+
+    impl Serialize for Decimal {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Decimal {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_str(DecimalVisitor)
+        }
+    }
+
+    struct DecimalVisitor;
+
+    impl Visitor<'_> for DecimalVisitor {
+        type Value = Decimal;
+
+        fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str("a string holding a decimal number")
         }
 
-        let mut negative = false;
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+            value.parse().map_err(E::custom)
+        }
+    }
+
+    /// Serialize decimals as numbers instead, with
+    /// `#[serde(with = "decimal::serde::number")]`. Numbers are written as
+    /// floats, so digits beyond their precision are lost. Both numbers and
+    /// strings are accepted when deserializing, but numbers the format reads
+    /// as floats are only as precise as those.
+    pub mod number {
+        use super::super::{Decimal, FloatConversion, limbs_from_u128};
+        use ::serde::Serializer;
+        use ::serde::de::{self, Deserializer, Visitor};
+        use std::fmt::{self, Formatter};
+
+        pub fn serialize<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_f64(value.to_f64_lossy())
+        }
 
-        // Multiplying two negative decimals results in a positive decimal.
-        if (self.negative || rhs.negative) && !(self.negative && rhs.negative) {
-            negative = true
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Decimal, D::Error> {
+            deserializer.deserialize_any(NumberVisitor)
         }
 
-        // Perform long multiplication.
-        let mut product = vec![0; self.digits.len() + rhs.digits.len()];
+        struct NumberVisitor;
+
+        impl Visitor<'_> for NumberVisitor {
+            type Value = Decimal;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a decimal number or a string holding one")
+            }
 
-        for (i, r) in rhs.digits.iter().enumerate().rev() {
-            let mut carry = 0;
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+                Ok(Decimal::from(value))
+            }
 
-            for (j, l) in self.digits.iter().enumerate().rev() {
-                product[i + j + 1] += l * r + carry;
-                carry = product[i + j + 1] / 10;
-                product[i + j + 1] %= 10;
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+                Ok(Decimal::from(value))
             }
 
-            product[i] = carry
+            fn visit_i128<E: de::Error>(self, value: i128) -> Result<Decimal, E> {
+                Ok(Decimal::from(value))
+            }
+
+            fn visit_u128<E: de::Error>(self, value: u128) -> Result<Decimal, E> {
+                Ok(Decimal::from_parts(false, limbs_from_u128(value), 0))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+                Decimal::from_f64(value, FloatConversion::Shortest).map_err(E::custom)
+            }
         }
+    }
+}
 
-        let mut result = String::new();
+#[cfg(test)]
+mod tests {
+    use super::model::Model;
+    use super::*;
+    use num_bigint::BigInt;
+    use proptest::prelude::*;
+    use std::hash::DefaultHasher;
+
+    // Both short numbers and ones long enough for Karatsuba multiplication.
+    fn model() -> impl Strategy<Value = Model> {
+        let digits = prop_oneof!["[0-9]{1,30}", "[0-9]{290,400}"];
+
+        (any::<bool>(), digits, 0..40u32).prop_map(|(negative, digits, scale)| {
+            let mantissa = digits.parse::<BigInt>().unwrap();
+            Model::new(if negative { -mantissa } else { mantissa }, scale)
+        })
+    }
+
+    // Small numbers, which often divide others exactly or to a tie.
+    fn small_model() -> impl Strategy<Value = Model> {
+        (-20..=20i32, 0..3u32).prop_map(|(mantissa, scale)| Model::new(mantissa.into(), scale))
+    }
 
-        for (idx, n) in product.iter().enumerate() {
-            if idx == product.len() - (self.fractional().len() + rhs.fractional().len()) {
-                result.push('.')
+    fn rounding_mode() -> impl Strategy<Value = RoundingMode> {
+        prop_oneof![
+            Just(RoundingMode::HalfEven),
+            Just(RoundingMode::HalfUp),
+            Just(RoundingMode::Floor),
+            Just(RoundingMode::Ceiling),
+            Just(RoundingMode::Truncate),
+        ]
+    }
+
+    fn decimal(model: &Model) -> Decimal {
+        model.to_padded_string(0, 0).parse().unwrap()
+    }
+
+    fn hash(decimal: &Decimal) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        decimal.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    proptest! {
+        #[test]
+        fn parse_and_display(a in model(), leading in 0..4usize, trailing in 0..4usize) {
+            let padded = a.to_padded_string(leading, trailing).parse::<Decimal>().unwrap();
+
+            prop_assert_eq!(padded.to_string(), a.to_canonical_string());
+            prop_assert_eq!(&padded, &decimal(&a));
+            prop_assert_eq!(hash(&padded), hash(&decimal(&a)));
+        }
+
+        #[test]
+        fn arithmetic(a in model(), b in model()) {
+            let (x, y) = (decimal(&a), decimal(&b));
+
+            prop_assert_eq!((&x + &y).to_string(), a.add(&b).to_canonical_string());
+            prop_assert_eq!((&x - &y).to_string(), a.sub(&b).to_canonical_string());
+            prop_assert_eq!((&x * &y).to_string(), a.mul(&b).to_canonical_string());
+        }
+
+        #[test]
+        fn ordering(a in model(), b in model()) {
+            let (x, y) = (decimal(&a), decimal(&b));
+
+            prop_assert_eq!(x.cmp(&y), a.cmp(&b));
+            prop_assert_eq!(x == y, a.cmp(&b).is_eq());
+            prop_assert_eq!(y.cmp(&x), x.cmp(&y).reverse());
+        }
+
+        #[test]
+        fn laws(a in model(), b in model(), c in model()) {
+            let (x, y, z) = (decimal(&a), decimal(&b), decimal(&c));
+            let zero = Decimal::default();
+            let one = Decimal::from(1i64);
+
+            prop_assert_eq!(&x + &y, &y + &x);
+            prop_assert_eq!(&x * &y, &y * &x);
+            prop_assert_eq!(&(&x + &y) + &z, &x + &(&y + &z));
+            prop_assert_eq!(&(&x * &y) * &z, &x * &(&y * &z));
+            prop_assert_eq!(&x * &(&y + &z), &(&x * &y) + &(&x * &z));
+            prop_assert_eq!(&(&x - &y) + &y, x.clone());
+            prop_assert_eq!(&x + &zero, x.clone());
+            prop_assert_eq!(&x * &one, x.clone());
+            prop_assert_eq!(&x + &-&x, zero);
+        }
+
+        #[test]
+        fn division(
+            a in model(),
+            b in prop_oneof![model(), small_model()],
+            scale in 0..40u32,
+            mode in rounding_mode(),
+        ) {
+            let quotient = decimal(&a).checked_div(&decimal(&b), scale as usize, mode);
+            let expected = a.div(&b, scale, mode);
+
+            prop_assert_eq!(
+                quotient.map(|quotient| quotient.to_string()),
+                expected.map(|expected| expected.to_canonical_string())
+            );
+        }
+
+        #[test]
+        fn rounding(a in model(), scale in 0..40u32, mode in rounding_mode()) {
+            let rounded = decimal(&a).round(scale as usize, mode);
+
+            prop_assert_eq!(rounded.to_string(), a.round(scale, mode).to_canonical_string());
+        }
+
+        #[test]
+        fn rounding_ties(a in model(), zeros in 0..4u32, mode in rounding_mode()) {
+            // Followed by a five and some zeros, the value is exactly halfway
+            // between two values at its original scale.
+            let shift = BigInt::from(10).pow(zeros);
+            let sign = if a.mantissa < BigInt::ZERO { -1 } else { 1 };
+            let mantissa = (&a.mantissa * 10 + sign * 5) * shift;
+            let tie = Model::new(mantissa, a.scale + zeros + 1);
+
+            let rounded = decimal(&tie).round(a.scale as usize, mode);
+
+            prop_assert_eq!(rounded.to_string(), tie.round(a.scale, mode).to_canonical_string());
+        }
+
+        #[test]
+        fn square_root(a in model(), precision in 0..30u32) {
+            let root = decimal(&a).sqrt(precision as usize);
+
+            match root {
+                None => prop_assert!(decimal(&a) < Decimal::default()),
+                Some(root) => {
+                    let root = Model::parse(&root.to_string());
+                    prop_assert!(a.is_rounded_sqrt(&root, precision));
+                }
             }
+        }
+    }
+
+    #[test]
+    fn format() {
+        let value = "1234.5".parse::<Decimal>().unwrap();
+        let grouped = Format::new().with_grouping(',').with_scale(2);
+
+        assert_eq!(value.format(&grouped), "1,234.50");
+        assert_eq!(
+            value.format(&grouped.with_decimal_mark(',').with_grouping('.')),
+            "1.234,50"
+        );
+        assert_eq!(
+            "-0.004"
+                .parse::<Decimal>()
+                .unwrap()
+                .format(&Format::new().with_decimal_mark(',').with_scale(2)),
+            "0,00"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_as_string() {
+        let value = "-1234.5678".parse::<Decimal>().unwrap();
+        let json = serde_json::to_string(&value).unwrap();
 
-            result.push_str(&n.to_string())
+        assert_eq!(json, r#""-1234.5678""#);
+        assert_eq!(serde_json::from_str::<Decimal>(&json).unwrap(), value);
+        assert!(serde_json::from_str::<Decimal>("1.5").is_err());
+        assert!(
+            serde_json::from_str::<std::collections::HashMap<String, Decimal>>(r#"{"a":1.5}"#)
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_as_number() {
+        use ::serde::de::IntoDeserializer;
+        use ::serde::de::value::Error;
+
+        fn from_json(json: &str) -> Decimal {
+            serde::number::deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap()
         }
 
-        Decimal::try_from(&format!("{}{result}", if negative { "-" } else { "" }))
-            .expect("Should be a valid decimal number.")
+        assert_eq!(from_json("1.5"), "1.5".parse().unwrap());
+        assert_eq!(from_json("-42"), Decimal::from(-42i64));
+        assert_eq!(from_json(r#""1e2""#), Decimal::from(100i64));
+
+        let max: Result<Decimal, Error> = serde::number::deserialize(u128::MAX.into_deserializer());
+        assert_eq!(max.unwrap().to_string(), u128::MAX.to_string());
+
+        let mut json = vec![];
+        let value = "2.25".parse::<Decimal>().unwrap();
+        serde::number::serialize(&value, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert_eq!(json, b"2.25");
     }
-}
\ No newline at end of file
+}
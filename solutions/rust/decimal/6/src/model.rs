@@ -0,0 +1,168 @@
+//! Reference model of decimal arithmetic for tests, independent of the limb
+//! code it checks: a value is an arbitrary precision integer number of units
+//! of `10^-scale`.
+//!
+//! The fuzz targets include this file with `#[path]`, so it only refers to
+//! the crate through `super`.
+
+use super::RoundingMode;
+use num_bigint::{BigInt, Sign};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub mantissa: BigInt,
+    pub scale: u32,
+}
+
+impl Model {
+    pub fn new(mantissa: BigInt, scale: u32) -> Self {
+        Model { mantissa, scale }
+    }
+
+    /// Parse plain decimal notation, such as the output of `Display`.
+    pub fn parse(text: &str) -> Model {
+        let (integer, fractional) = text.split_once('.').unwrap_or((text, ""));
+        let mantissa = format!("{integer}{fractional}").parse().unwrap();
+
+        Model::new(mantissa, fractional.len() as u32)
+    }
+
+    /// Plain decimal notation with extra leading and trailing zeros.
+    pub fn to_padded_string(&self, leading: usize, trailing: usize) -> String {
+        let scale = self.scale as usize;
+        let digits = self.mantissa.magnitude().to_string();
+        let digits = "0".repeat(leading + (scale + 1).saturating_sub(digits.len())) + &digits;
+        let (integer, fractional) = digits.split_at(digits.len() - scale);
+        let sign = if self.mantissa.sign() == Sign::Minus {
+            "-"
+        } else {
+            ""
+        };
+
+        match scale + trailing {
+            0 => format!("{sign}{integer}"),
+            _ => format!("{sign}{integer}.{fractional}{}", "0".repeat(trailing)),
+        }
+    }
+
+    /// Plain decimal notation without redundant zeros, as `Display` prints it.
+    pub fn to_canonical_string(&self) -> String {
+        let mut model = self.clone();
+        let ten = BigInt::from(10);
+
+        while model.scale > 0 && (&model.mantissa % &ten).sign() == Sign::NoSign {
+            model.mantissa /= &ten;
+            model.scale -= 1;
+        }
+
+        model.to_padded_string(0, 0)
+    }
+
+    fn aligned(&self, other: &Model) -> (BigInt, BigInt, u32) {
+        let scale = self.scale.max(other.scale);
+
+        (
+            &self.mantissa * pow10(scale - self.scale),
+            &other.mantissa * pow10(scale - other.scale),
+            scale,
+        )
+    }
+
+    pub fn add(&self, other: &Model) -> Model {
+        let (lhs, rhs, scale) = self.aligned(other);
+        Model::new(lhs + rhs, scale)
+    }
+
+    pub fn sub(&self, other: &Model) -> Model {
+        let (lhs, rhs, scale) = self.aligned(other);
+        Model::new(lhs - rhs, scale)
+    }
+
+    pub fn mul(&self, other: &Model) -> Model {
+        Model::new(&self.mantissa * &other.mantissa, self.scale + other.scale)
+    }
+
+    pub fn cmp(&self, other: &Model) -> Ordering {
+        let (lhs, rhs, _) = self.aligned(other);
+        lhs.cmp(&rhs)
+    }
+
+    /// The quotient rounded to the given scale, or `None` when dividing by zero.
+    pub fn div(&self, other: &Model, scale: u32, mode: RoundingMode) -> Option<Model> {
+        if other.mantissa.sign() == Sign::NoSign {
+            return None;
+        }
+
+        let numerator = &self.mantissa * pow10(scale + other.scale);
+        let denominator = &other.mantissa * pow10(self.scale);
+
+        Some(Model::new(round_div(numerator, denominator, mode), scale))
+    }
+
+    pub fn round(&self, scale: u32, mode: RoundingMode) -> Model {
+        if self.scale <= scale {
+            return self.clone();
+        }
+
+        let mantissa = round_div(self.mantissa.clone(), pow10(self.scale - scale), mode);
+
+        Model::new(mantissa, scale)
+    }
+
+    /// Whether the root is the square root of the value rounded half up
+    /// to the given precision. For a root of `r / 10^p` this holds when
+    /// `(2r - 1)^2 <= 4 * value * 10^2p < (2r + 1)^2`.
+    pub fn is_rounded_sqrt(&self, root: &Model, precision: u32) -> bool {
+        let root = &root.mantissa * pow10(precision - root.scale);
+        let radicand = &self.mantissa * 4 * pow10(2 * precision);
+        let scaled = |bound: BigInt| &bound * &bound * pow10(self.scale);
+
+        let below = root.sign() == Sign::NoSign || scaled(&root * 2 - 1) <= radicand;
+
+        below && radicand < scaled(&root * 2 + 1)
+    }
+}
+
+fn pow10(exponent: u32) -> BigInt {
+    BigInt::from(10).pow(exponent)
+}
+
+/// The numerator divided by the denominator, rounded to an integer.
+fn round_div(numerator: BigInt, denominator: BigInt, mode: RoundingMode) -> BigInt {
+    let (numerator, denominator) = match denominator.sign() {
+        Sign::Minus => (-numerator, -denominator),
+        _ => (numerator, denominator),
+    };
+
+    let mut floor = &numerator / &denominator;
+    let mut remainder = &numerator % &denominator;
+
+    if remainder.sign() == Sign::Minus {
+        floor -= 1;
+        remainder += &denominator;
+    }
+
+    if remainder.sign() == Sign::NoSign {
+        return floor;
+    }
+
+    let negative = numerator.sign() == Sign::Minus;
+    let ceiling = &floor + 1;
+
+    match mode {
+        RoundingMode::Floor => floor,
+        RoundingMode::Ceiling => ceiling,
+        RoundingMode::Truncate if negative => ceiling,
+        RoundingMode::Truncate => floor,
+        RoundingMode::HalfUp | RoundingMode::HalfEven => match (remainder * 2u32).cmp(&denominator)
+        {
+            Ordering::Less => floor,
+            Ordering::Greater => ceiling,
+            Ordering::Equal if mode == RoundingMode::HalfUp && negative => floor,
+            Ordering::Equal if mode == RoundingMode::HalfUp => ceiling,
+            Ordering::Equal if (&floor % 2u32).sign() == Sign::NoSign => floor,
+            Ordering::Equal => ceiling,
+        },
+    }
+}
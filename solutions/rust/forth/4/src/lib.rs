@@ -1,29 +1,1023 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
+use std::ops::{BitAnd, BitOr, Not, Range};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// The default cell type.
 pub type Value = i32;
 pub type Result = std::result::Result<(), Error>;
+/// The data stack as seen by native words, top of stack last.
+pub type Stack<V = Value> = Vec<V>;
+/// A word implemented by the host, see `Forth::register_native`.
+pub type Native<V = Value> = fn(&mut Stack<V>) -> Result;
+
+/// An integer type the interpreter can use for its cells.
+pub trait Cell:
+    Copy
+    + Ord
+    + Debug
+    + Display
+    + FromStr
+    + Not<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    /// The canonical true flag, with all bits set.
+    const TRUE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+    fn checked_neg(self) -> Option<Self>;
+    fn checked_abs(self) -> Option<Self>;
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    fn wrapping_div(self, rhs: Self) -> Self;
+    fn wrapping_rem(self, rhs: Self) -> Self;
+    fn wrapping_neg(self) -> Self;
+    fn wrapping_abs(self) -> Self;
+    fn to_usize(self) -> Option<usize>;
+    fn from_usize(n: usize) -> Option<Self>;
+}
+
+macro_rules! cell {
+    ($t:ty) => {
+        impl Cell for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const TRUE: Self = -1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+
+            fn checked_div(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_div(self, rhs)
+            }
+
+            fn checked_rem(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_rem(self, rhs)
+            }
+
+            fn checked_neg(self) -> Option<Self> {
+                <$t>::checked_neg(self)
+            }
+
+            fn checked_abs(self) -> Option<Self> {
+                <$t>::checked_abs(self)
+            }
+
+            fn wrapping_add(self, rhs: Self) -> Self {
+                <$t>::wrapping_add(self, rhs)
+            }
+
+            fn wrapping_sub(self, rhs: Self) -> Self {
+                <$t>::wrapping_sub(self, rhs)
+            }
+
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                <$t>::wrapping_mul(self, rhs)
+            }
+
+            fn wrapping_div(self, rhs: Self) -> Self {
+                <$t>::wrapping_div(self, rhs)
+            }
+
+            fn wrapping_rem(self, rhs: Self) -> Self {
+                <$t>::wrapping_rem(self, rhs)
+            }
+
+            fn wrapping_neg(self) -> Self {
+                <$t>::wrapping_neg(self)
+            }
+
+            fn wrapping_abs(self) -> Self {
+                <$t>::wrapping_abs(self)
+            }
+
+            fn to_usize(self) -> Option<usize> {
+                usize::try_from(self).ok()
+            }
+
+            fn from_usize(n: usize) -> Option<Self> {
+                <$t>::try_from(n).ok()
+            }
+        }
+    };
+}
+
+cell!(i32);
+cell!(i64);
+
+/// Number of cells available in data memory.
+const MEMORY_SIZE: usize = 1 << 16;
+
+/// Resource limits for evaluating untrusted code. Limits set to `None`
+/// are not enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Maximum number of instructions executed by a single `eval`.
+    pub max_steps: Option<usize>,
+    /// Maximum number of values on the stack.
+    pub max_stack_depth: Option<usize>,
+    /// Maximum number of words in the dictionary.
+    pub max_dictionary_size: Option<usize>,
+    /// Maximum number of nested calls plus values on the return stack.
+    pub max_return_stack_depth: Option<usize>,
+}
+
+/// First line of a serialized snapshot, identifying the format version.
+const SNAPSHOT_HEADER: &str = "forth-snapshot 1";
+
+/// Where printing words write to.
+#[derive(Clone)]
+enum Output {
+    /// Collect output in memory, see `Forth::output`.
+    Buffer(String),
+    /// Write output to a caller-supplied sink, shared between clones.
+    Sink(Arc<Mutex<dyn Write + Send>>),
+}
+
+/// What to do when an arithmetic operation does not fit into a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Fail with `Error::Overflow`.
+    #[default]
+    Error,
+    /// Wrap around at the boundary of the cell type.
+    Wrap,
+}
+
+/// Built-in operations known to the interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitive {
+    Swap,
+    Dup,
+    Drop,
+    Over,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    DivMod,
+    Negate,
+    Abs,
+    Eq,
+    Lt,
+    Gt,
+    And,
+    Or,
+    Invert,
+    Rot,
+    Nip,
+    Tuck,
+    Pick,
+    Roll,
+    TwoDup,
+    TwoDrop,
+    Dot,
+    DotS,
+    Emit,
+    Cr,
+    Type,
+    Fetch,
+    Store,
+    Allot,
+    Comma,
+    Here,
+    Count,
+    I,
+    J,
+}
+
+/// Names of the built-in operations, in the order `WORDS` lists them.
+const PRIMITIVES: [(&str, Primitive); 38] = [
+    ("swap", Primitive::Swap),
+    ("dup", Primitive::Dup),
+    ("drop", Primitive::Drop),
+    ("over", Primitive::Over),
+    ("+", Primitive::Add),
+    ("-", Primitive::Sub),
+    ("*", Primitive::Mul),
+    ("/", Primitive::Div),
+    ("mod", Primitive::Mod),
+    ("/mod", Primitive::DivMod),
+    ("negate", Primitive::Negate),
+    ("abs", Primitive::Abs),
+    ("=", Primitive::Eq),
+    ("<", Primitive::Lt),
+    (">", Primitive::Gt),
+    ("and", Primitive::And),
+    ("or", Primitive::Or),
+    ("invert", Primitive::Invert),
+    ("rot", Primitive::Rot),
+    ("nip", Primitive::Nip),
+    ("tuck", Primitive::Tuck),
+    ("pick", Primitive::Pick),
+    ("roll", Primitive::Roll),
+    ("2dup", Primitive::TwoDup),
+    ("2drop", Primitive::TwoDrop),
+    (".", Primitive::Dot),
+    (".s", Primitive::DotS),
+    ("emit", Primitive::Emit),
+    ("cr", Primitive::Cr),
+    ("type", Primitive::Type),
+    ("@", Primitive::Fetch),
+    ("!", Primitive::Store),
+    ("allot", Primitive::Allot),
+    (",", Primitive::Comma),
+    ("here", Primitive::Here),
+    ("count", Primitive::Count),
+    ("i", Primitive::I),
+    ("j", Primitive::J),
+];
+
+impl Primitive {
+    fn from_name(name: &str) -> Option<Primitive> {
+        PRIMITIVES
+            .iter()
+            .find(|(primitive_name, _)| *primitive_name == name)
+            .map(|(_, primitive)| *primitive)
+    }
+
+    fn name(self) -> &'static str {
+        PRIMITIVES
+            .iter()
+            .find(|(_, primitive)| *primitive == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+}
+
+/// Words handled by the compiler rather than executed as built-in operations.
+const COMPILER_WORDS: [&str; 28] = [
+    ":", ";", "(", "\\", "if", "else", "then", "begin", "until", "while", "repeat", "do", "loop",
+    ".\"", "s\"", "c\"", "char", "[char]", "variable", "constant", "words", "see", "forget",
+    "recurse", "exit", ">r", "r>", "r@",
+];
+
+/// A piece of source code and its position in it.
+struct Token<'a> {
+    text: &'a str,
+    span: Range<usize>,
+}
+
+/// Splits source code into whitespace-separated tokens, skipping
+/// `( ... )` and `\ ...` comments.
+struct Tokens<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(source: &'a str) -> Self {
+        Tokens {
+            source,
+            position: 0,
+        }
+    }
 
-struct Word {
-    id: usize,
+    /// Read raw text up to the delimiter, which is consumed. A single space
+    /// separating the text from the previous token is skipped.
+    fn until(&mut self, delimiter: char) -> Option<&'a str> {
+        let rest = &self.source[self.position..];
+        let start = match rest.chars().next() {
+            Some(c) if c.is_whitespace() => self.position + c.len_utf8(),
+            _ => self.position,
+        };
+
+        let end = start + self.source[start..].find(delimiter)?;
+        self.position = end + delimiter.len_utf8();
+
+        Some(&self.source[start..end])
+    }
+
+    /// Skip everything up to and including the delimiter, or up to
+    /// the end of the source if there is none.
+    fn skip_past(&mut self, delimiter: char) {
+        self.position = self.source[self.position..]
+            .find(delimiter)
+            .map_or(self.source.len(), |index| {
+                self.position + index + delimiter.len_utf8()
+            });
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.source[self.position..];
+            let start = self.position + rest.find(|c: char| !c.is_whitespace())?;
+            let rest = &self.source[start..];
+
+            self.position = start + rest.find(char::is_whitespace).unwrap_or(rest.len());
+
+            match &self.source[start..self.position] {
+                "(" => self.skip_past(')'),
+                "\\" => self.skip_past('\n'),
+                text => {
+                    return Some(Token {
+                        text,
+                        span: start..self.position,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Read the name of a word defined by `VARIABLE` or `CONSTANT`. Names are
+/// read when compiling, so `;` must not be taken for one.
+fn definition_name<'a>(tokens: &mut Tokens<'a>) -> std::result::Result<&'a str, Error> {
+    match tokens.next() {
+        Some(name) if name.text != ":" && name.text != ";" => Ok(name.text),
+        _ => Err(Error::InvalidWord),
+    }
+}
+
+/// A single compiled operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Instruction<V> {
+    /// Push a number onto the stack.
+    Literal(V),
+    /// Execute a built-in operation.
+    Primitive(Primitive),
+    /// Write a string to the output.
+    Print(Arc<str>),
+    /// Push the address and length of the characters of the counted string
+    /// stored at the given address. The text is kept for decompiling.
+    String(V, Arc<str>),
+    /// Push the address of the counted string stored at the given address.
+    CountedString(V, Arc<str>),
+    /// Define a word pushing the address of a newly allocated cell.
+    Variable(Arc<str>),
+    /// Pop a value and define a word pushing it.
+    Constant(Arc<str>),
+    /// Print the names of all known words.
+    Words,
+    /// Print the definition of a word.
+    See(Arc<str>),
+    /// Remove a word and every word defined after it from the dictionary.
+    Forget(Arc<str>),
+    /// Execute a user-defined word by its dictionary id.
+    Call(usize),
+    /// Execute a native word by its registration id.
+    Native(usize),
+    /// Continue execution at the given position.
+    Branch(usize),
+    /// Pop a value and continue execution at the given position if it is zero.
+    BranchIfZero(usize),
+    /// Pop a start index and a limit and enter a counted loop.
+    Do,
+    /// Increment the loop index and continue execution at the given position
+    /// (the start of the loop body) until the index reaches the limit.
+    Loop(usize),
+    /// Return from the word being executed.
+    Exit,
+    /// Move a value from the stack to the return stack.
+    ToReturn,
+    /// Move a value from the return stack to the stack.
+    FromReturn,
+    /// Copy the topmost value of the return stack to the stack.
+    FetchReturn,
+}
+
+/// Compiled code of a word or of a piece of input.
+type Code<V> = Arc<[Instruction<V>]>;
+
+/// An open control structure waiting for its closing word.
+enum Control {
+    /// `IF` with the position of its conditional branch.
+    If(usize),
+    /// `ELSE` with the position of its unconditional branch.
+    Else(usize),
+    /// `BEGIN` with the position of the loop start.
+    Begin(usize),
+    /// `WHILE` with the position of the loop start and of its conditional branch.
+    While(usize, usize),
+    /// `DO` with the position of the loop body start.
+    Do(usize),
+}
+
+/// Translates tokens into instructions, resolving control structures
+/// into branches.
+struct Compiler<V> {
+    code: Vec<Instruction<V>>,
+    control: Vec<Control>,
+    /// Source position of the token each instruction was compiled from.
+    spans: Vec<Range<usize>>,
+    /// Source position of the opening word of each open control structure.
+    openers: Vec<Range<usize>>,
+    /// Dictionary id the word being defined will get, if any.
+    word: Option<usize>,
+}
+
+impl<V: Cell> Compiler<V> {
+    fn new(word: Option<usize>) -> Self {
+        Compiler {
+            code: vec![],
+            control: vec![],
+            spans: vec![],
+            openers: vec![],
+            word,
+        }
+    }
+
+    /// Compile a token, keeping track of where in the source the resulting
+    /// instructions and control structures come from.
+    fn compile(&mut self, forth: &mut Forth<V>, token: &Token, tokens: &mut Tokens) -> Result {
+        self.compile_token(forth, token.text, tokens)?;
+
+        // A token opens or closes at most one control structure.
+        self.spans.resize(self.code.len(), token.span.clone());
+        self.openers.resize(self.control.len(), token.span.clone());
+
+        Ok(())
+    }
+
+    /// Compile a token and append the result to the code. User-defined words
+    /// take precedence over control words and built-in operations, which take
+    /// precedence over numbers.
+    fn compile_token(&mut self, forth: &mut Forth<V>, token: &str, tokens: &mut Tokens) -> Result {
+        let token = token.to_ascii_lowercase();
+
+        if let Some(id) = forth.find_word(&token) {
+            self.code.push(Instruction::Call(id));
+            return Ok(());
+        }
+
+        match token.as_str() {
+            "if" => {
+                self.control.push(Control::If(self.code.len()));
+                self.code.push(Instruction::BranchIfZero(0));
+            }
+            "else" => {
+                let Some(Control::If(branch)) = self.control.pop() else {
+                    return Err(Error::UnbalancedControl);
+                };
+
+                self.control.push(Control::Else(self.code.len()));
+                self.code.push(Instruction::Branch(0));
+                self.code[branch] = Instruction::BranchIfZero(self.code.len());
+            }
+            "then" => match self.control.pop() {
+                Some(Control::If(branch)) => {
+                    self.code[branch] = Instruction::BranchIfZero(self.code.len())
+                }
+                Some(Control::Else(branch)) => {
+                    self.code[branch] = Instruction::Branch(self.code.len())
+                }
+                _ => return Err(Error::UnbalancedControl),
+            },
+            "begin" => self.control.push(Control::Begin(self.code.len())),
+            "until" => {
+                let Some(Control::Begin(start)) = self.control.pop() else {
+                    return Err(Error::UnbalancedControl);
+                };
+
+                self.code.push(Instruction::BranchIfZero(start));
+            }
+            "while" => {
+                let Some(Control::Begin(start)) = self.control.pop() else {
+                    return Err(Error::UnbalancedControl);
+                };
+
+                self.control.push(Control::While(start, self.code.len()));
+                self.code.push(Instruction::BranchIfZero(0));
+            }
+            "repeat" => {
+                let Some(Control::While(start, branch)) = self.control.pop() else {
+                    return Err(Error::UnbalancedControl);
+                };
+
+                self.code.push(Instruction::Branch(start));
+                self.code[branch] = Instruction::BranchIfZero(self.code.len());
+            }
+            ".\"" => {
+                let text = tokens.until('"').ok_or(Error::UnterminatedString)?;
+                self.code.push(Instruction::Print(text.into()));
+            }
+            // String literals are stored in data memory once, when compiled.
+            "s\"" => {
+                let text = tokens.until('"').ok_or(Error::UnterminatedString)?;
+                let address = forth.store_string(text)?;
+                self.code.push(Instruction::String(address, text.into()));
+            }
+            "c\"" => {
+                let text = tokens.until('"').ok_or(Error::UnterminatedString)?;
+                let address = forth.store_string(text)?;
+                self.code
+                    .push(Instruction::CountedString(address, text.into()));
+            }
+            "char" | "[char]" => {
+                let name = tokens.next().ok_or(Error::InvalidWord)?;
+                let c = name.text.chars().next().unwrap();
+                let value = V::from_usize(c as usize).ok_or(Error::Overflow)?;
+                self.code.push(Instruction::Literal(value));
+            }
+            "variable" => {
+                let name = definition_name(tokens)?;
+                self.code.push(Instruction::Variable(name.into()));
+            }
+            "constant" => {
+                let name = definition_name(tokens)?;
+                self.code.push(Instruction::Constant(name.into()));
+            }
+            "words" => self.code.push(Instruction::Words),
+            "see" => {
+                let name = tokens.next().ok_or(Error::UnknownWord)?;
+                self.code.push(Instruction::See(name.text.into()));
+            }
+            "forget" => {
+                // Code compiled along with it would run after the forgotten
+                // ids are reused by new definitions, calling the wrong words.
+                if !self.control.is_empty() {
+                    return Err(Error::MisplacedControl);
+                }
+
+                let name = tokens.next().ok_or(Error::UnknownWord)?;
+                self.code.push(Instruction::Forget(name.text.into()));
+            }
+            "do" => {
+                self.code.push(Instruction::Do);
+                self.control.push(Control::Do(self.code.len()));
+            }
+            "loop" => {
+                let Some(Control::Do(start)) = self.control.pop() else {
+                    return Err(Error::UnbalancedControl);
+                };
+
+                self.code.push(Instruction::Loop(start));
+            }
+            "recurse" => {
+                let id = self.word.ok_or(Error::MisplacedControl)?;
+                self.code.push(Instruction::Call(id));
+            }
+            "exit" => {
+                self.word.ok_or(Error::MisplacedControl)?;
+                self.code.push(Instruction::Exit);
+            }
+            // The return stack doesn't outlive a top-level piece of code,
+            // so it is only of use inside a definition.
+            ">r" | "r>" | "r@" => {
+                self.word.ok_or(Error::MisplacedControl)?;
+                self.code.push(match &*token {
+                    ">r" => Instruction::ToReturn,
+                    "r>" => Instruction::FromReturn,
+                    _ => Instruction::FetchReturn,
+                });
+            }
+            _ => {
+                let instruction = match Primitive::from_name(&token) {
+                    Some(primitive) => Instruction::Primitive(primitive),
+                    None => token
+                        .parse::<V>()
+                        .map(Instruction::Literal)
+                        .map_err(|_| Error::UnknownWord)?,
+                };
+
+                self.code.push(instruction);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether all control structures have been closed.
+    fn is_balanced(&self) -> bool {
+        self.control.is_empty()
+    }
+
+    /// Take the compiled code along with the source position of each
+    /// instruction, leaving the compiler empty.
+    fn finish(&mut self) -> std::result::Result<(Code<V>, Vec<Range<usize>>), Error> {
+        if !self.is_balanced() {
+            return Err(Error::UnbalancedControl);
+        }
+
+        Ok((
+            std::mem::take(&mut self.code).into(),
+            std::mem::take(&mut self.spans),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Word<V> {
     name: String,
-    body: String,
+    body: Code<V>,
+    /// Source text of the token each instruction of the body was compiled
+    /// from. Empty for words not compiled from source, such as natives.
+    tokens: Arc<[Arc<str>]>,
 }
 
-impl Word {
-    pub fn new(id: usize, name: &str, body: &str) -> Self {
+impl<V> Word<V> {
+    pub fn new(name: &str, body: Code<V>) -> Self {
         Word {
-            id,
-            name: name.to_owned(),
-            body: body.to_owned(),
+            name: name.to_ascii_lowercase(),
+            body,
+            tokens: Arc::new([]),
         }
     }
 
-    fn symbol(&self) -> String {
-        format!("${}", self.id)
+    pub fn with_tokens(mut self, tokens: Arc<[Arc<str>]>) -> Self {
+        self.tokens = tokens;
+        self
     }
 }
 
-pub struct Forth {
-    dictionary: Vec<Word>,
-    stack: Vec<Value>,
+/// Compiled code being executed and the position of the next instruction in it.
+struct Frame<V> {
+    code: Code<V>,
+    pc: usize,
+    /// Dictionary id of the word being executed, if any.
+    word: Option<usize>,
+    /// Number of running loops when the frame was entered.
+    loops: usize,
+    /// Depth of the return stack when the frame was entered. Values below
+    /// it belong to the caller.
+    returns: usize,
+}
+
+/// An error raised while running compiled code.
+struct Fault {
+    error: Error,
+    /// Position of the failed instruction in the outermost code.
+    pc: usize,
+    /// Names of the user-defined words being executed, outermost first.
+    call_chain: Vec<String>,
+    /// Source text of the failed instruction, if it belongs to a
+    /// user-defined word.
+    inner_token: Option<Arc<str>>,
+}
+
+/// An error along with where in the evaluated input it happened.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub error: Error,
+    /// Byte range of the offending token in the input.
+    pub span: Range<usize>,
+    /// Text of the offending token.
+    pub token: String,
+    /// Names of the user-defined words being executed when the error
+    /// happened, outermost first.
+    pub call_chain: Vec<String>,
+    /// Text of the failed token inside the innermost user-defined word
+    /// being executed, if any. `token` is then the word called from the input.
+    pub inner_token: Option<String>,
+    source: String,
+}
+
+impl Diagnostic {
+    fn new(error: Error, source: &str, span: Range<usize>) -> Self {
+        Diagnostic {
+            error,
+            token: source[span.clone()].to_owned(),
+            span,
+            call_chain: vec![],
+            inner_token: None,
+            source: source.to_owned(),
+        }
+    }
+
+    /// Report an error raised while running code compiled from the given span.
+    fn from_fault(fault: Fault, source: &str, span: Range<usize>) -> Self {
+        Diagnostic {
+            call_chain: fault.call_chain,
+            inner_token: fault.inner_token.map(|token| token.to_string()),
+            ..Diagnostic::new(fault.error, source, span)
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    /// Render the error with the offending line and the token underlined:
+    ///
+    /// ```text
+    /// error: stack underflow
+    ///  --> 1:3
+    ///   |
+    /// 1 | 1 foo
+    ///   |   ^^^
+    ///   = while executing: foo -> bar
+    ///   = failed at: dup
+    /// ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let line_start = self.source[..self.span.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let line_end = self.source[self.span.start..]
+            .find('\n')
+            .map_or(self.source.len(), |index| self.span.start + index);
+        let line = &self.source[line_start..line_end];
+
+        let line_number = self.source[..line_start].matches('\n').count() + 1;
+        let column = self.source[line_start..self.span.start].chars().count();
+        let width = self
+            .token
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .count()
+            .max(1);
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        writeln!(f, "error: {}", self.error)?;
+        writeln!(f, "{gutter}--> {line_number}:{}", column + 1)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{line_number} | {line}")?;
+        write!(f, "{gutter} | {}{}", " ".repeat(column), "^".repeat(width))?;
+
+        if !self.call_chain.is_empty() {
+            write!(
+                f,
+                "\n{gutter} = while executing: {}",
+                self.call_chain.join(" -> ")
+            )?;
+        }
+
+        if let Some(token) = &self.inner_token {
+            write!(f, "\n{gutter} = failed at: {token}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Saved state of an interpreter: its dictionary, stack and data memory.
+///
+/// Snapshots share compiled code with the interpreter, so taking and
+/// restoring them is cheap. They can be persisted as text with `to_string`
+/// and loaded back with `parse`. Native words are referred to by their
+/// registration order, so an interpreter restoring a snapshot should have
+/// registered the same natives in the same order.
+///
+/// Both snapshots and interpreters are `Send` and `Sync`, so a prepared
+/// snapshot can be shared by interpreters running on several threads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<V: Cell = Value> {
+    dictionary: Vec<Word<V>>,
+    stack: Vec<V>,
+    memory: Vec<V>,
+}
+
+impl<V: Cell> Display for Snapshot<V> {
+    /// Write the snapshot in a line-based text format:
+    ///
+    /// ```text
+    /// forth-snapshot 1
+    /// stack 1 2
+    /// memory 0
+    /// word square
+    /// tokens dup *
+    /// primitive dup
+    /// primitive *
+    /// end
+    /// ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{SNAPSHOT_HEADER}")?;
+        writeln!(f, "stack{}", cells_to_string(&self.stack))?;
+        writeln!(f, "memory{}", cells_to_string(&self.memory))?;
+
+        for word in &self.dictionary {
+            writeln!(f, "word {}", word.name)?;
+
+            if !word.tokens.is_empty() {
+                let tokens = word.tokens.iter().map(|token| format!(" {token}"));
+                writeln!(f, "tokens{}", tokens.collect::<String>())?;
+            }
+
+            for instruction in word.body.iter() {
+                match instruction {
+                    Instruction::Literal(value) => writeln!(f, "literal {value}"),
+                    Instruction::Primitive(primitive) => {
+                        writeln!(f, "primitive {}", primitive.name())
+                    }
+                    Instruction::Print(text) => writeln!(f, "print {}", escape(text)),
+                    Instruction::String(address, text) => {
+                        writeln!(f, "string {address} {}", escape(text))
+                    }
+                    Instruction::CountedString(address, text) => {
+                        writeln!(f, "counted-string {address} {}", escape(text))
+                    }
+                    Instruction::Variable(name) => writeln!(f, "variable {name}"),
+                    Instruction::Constant(name) => writeln!(f, "constant {name}"),
+                    Instruction::Words => writeln!(f, "words"),
+                    Instruction::See(name) => writeln!(f, "see {name}"),
+                    Instruction::Forget(name) => writeln!(f, "forget {name}"),
+                    Instruction::Call(id) => writeln!(f, "call {id}"),
+                    Instruction::Native(id) => writeln!(f, "native {id}"),
+                    Instruction::Branch(target) => writeln!(f, "branch {target}"),
+                    Instruction::BranchIfZero(target) => writeln!(f, "branch-if-zero {target}"),
+                    Instruction::Do => writeln!(f, "do"),
+                    Instruction::Loop(target) => writeln!(f, "loop {target}"),
+                    Instruction::Exit => writeln!(f, "exit"),
+                    Instruction::ToReturn => writeln!(f, "to-return"),
+                    Instruction::FromReturn => writeln!(f, "from-return"),
+                    Instruction::FetchReturn => writeln!(f, "fetch-return"),
+                }?;
+            }
+
+            writeln!(f, "end")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<V: Cell> FromStr for Snapshot<V> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut lines = s.lines().peekable();
+
+        if lines.next() != Some(SNAPSHOT_HEADER) {
+            return Err(Error::InvalidSnapshot);
+        }
+
+        let stack = parse_cells(lines.next().and_then(|line| line.strip_prefix("stack")))?;
+        let memory = parse_cells(lines.next().and_then(|line| line.strip_prefix("memory")))?;
+
+        if memory.len() > MEMORY_SIZE {
+            return Err(Error::InvalidSnapshot);
+        }
+
+        let mut dictionary = vec![];
+
+        while let Some(line) = lines.next() {
+            let name = line
+                .strip_prefix("word ")
+                .filter(|name| !name.is_empty() && !name.contains(char::is_whitespace))
+                .ok_or(Error::InvalidSnapshot)?;
+
+            let tokens: Vec<Arc<str>> = lines
+                .next_if(|line| line.starts_with("tokens"))
+                .map(|line| {
+                    line["tokens".len()..]
+                        .split_whitespace()
+                        .map(Arc::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let mut body = vec![];
+
+            loop {
+                match lines.next().ok_or(Error::InvalidSnapshot)? {
+                    "end" => break,
+                    line => body.push(parse_instruction(line, dictionary.len())?),
+                }
+            }
+
+            // Jumps may go anywhere in the body, or right past its end.
+            let jumps_inside = body.iter().all(|instruction| match instruction {
+                Instruction::Branch(target)
+                | Instruction::BranchIfZero(target)
+                | Instruction::Loop(target) => *target <= body.len(),
+                _ => true,
+            });
+
+            if !jumps_inside || (!tokens.is_empty() && tokens.len() != body.len()) {
+                return Err(Error::InvalidSnapshot);
+            }
+
+            dictionary.push(Word::new(name, body.into()).with_tokens(tokens.into()));
+        }
+
+        Ok(Snapshot {
+            dictionary,
+            stack,
+            memory,
+        })
+    }
+}
+
+fn cells_to_string<V: Cell>(cells: &[V]) -> String {
+    cells.iter().map(|cell| format!(" {cell}")).collect()
+}
+
+fn parse_cells<V: Cell>(line: Option<&str>) -> std::result::Result<Vec<V>, Error> {
+    line.ok_or(Error::InvalidSnapshot)?
+        .split_whitespace()
+        .map(|cell| cell.parse().map_err(|_| Error::InvalidSnapshot))
+        .collect()
+}
+
+/// Parse a serialized instruction of a word with the given dictionary id.
+/// Words may only call themselves and words defined before them.
+fn parse_instruction<V: Cell>(line: &str, id: usize) -> std::result::Result<Instruction<V>, Error> {
+    let (operation, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let position = || {
+        argument
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidSnapshot)
+    };
+
+    let instruction = match operation {
+        "literal" => Instruction::Literal(argument.parse().map_err(|_| Error::InvalidSnapshot)?),
+        "primitive" => {
+            Instruction::Primitive(Primitive::from_name(argument).ok_or(Error::InvalidSnapshot)?)
+        }
+        "print" => Instruction::Print(unescape(argument).ok_or(Error::InvalidSnapshot)?.into()),
+        "string" | "counted-string" => {
+            let (address, text) = argument.split_once(' ').ok_or(Error::InvalidSnapshot)?;
+            let address = address.parse().map_err(|_| Error::InvalidSnapshot)?;
+            let text = unescape(text).ok_or(Error::InvalidSnapshot)?.into();
+
+            match operation {
+                "string" => Instruction::String(address, text),
+                _ => Instruction::CountedString(address, text),
+            }
+        }
+        "variable" => Instruction::Variable(argument.into()),
+        "constant" => Instruction::Constant(argument.into()),
+        "words" => Instruction::Words,
+        "see" => Instruction::See(argument.into()),
+        "forget" => Instruction::Forget(argument.into()),
+        "call" => match position()? {
+            callee if callee <= id => Instruction::Call(callee),
+            _ => return Err(Error::InvalidSnapshot),
+        },
+        "native" => Instruction::Native(position()?),
+        "branch" => Instruction::Branch(position()?),
+        "branch-if-zero" => Instruction::BranchIfZero(position()?),
+        "do" => Instruction::Do,
+        "loop" => Instruction::Loop(position()?),
+        "exit" => Instruction::Exit,
+        "to-return" => Instruction::ToReturn,
+        "from-return" => Instruction::FromReturn,
+        "fetch-return" => Instruction::FetchReturn,
+        _ => return Err(Error::InvalidSnapshot),
+    };
+
+    Ok(instruction)
+}
+
+/// Escape backslashes and line breaks so that text fits on a single line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            '\\' => result.push('\\'),
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some(result)
+}
+
+#[derive(Clone)]
+pub struct Forth<V: Cell = Value> {
+    dictionary: Vec<Word<V>>,
+    stack: Vec<V>,
+    /// Index and limit of every running `DO` loop, innermost last.
+    loops: Vec<(V, V)>,
+    /// Values moved off the stack with `>R`.
+    return_stack: Vec<V>,
+    /// Data memory. Cells past its length are not allocated yet.
+    memory: Vec<V>,
+    /// Native words by registration id.
+    natives: Vec<Native<V>>,
+    overflow: Overflow,
+    output: Output,
+    limits: Limits,
+    /// Instructions executed by the current `eval`.
+    steps: usize,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -32,238 +1026,1485 @@ pub enum Error {
     StackUnderflow,
     UnknownWord,
     InvalidWord,
+    /// A control structure is not closed, or is closed by a word that
+    /// does not match its opening word (e.g. `IF` ... `LOOP`).
+    UnbalancedControl,
+    /// A word is used outside of the control structure it belongs to
+    /// (e.g. `I` outside of a `DO` loop, or `:` inside a definition).
+    MisplacedControl,
+    /// The result of an arithmetic operation does not fit into a cell.
+    Overflow,
+    /// A memory access or allocation falls outside of data memory.
+    InvalidAddress,
+    /// A string literal is missing its closing quote.
+    UnterminatedString,
+    /// Writing to the output sink failed.
+    Output,
+    /// An `eval` executed more instructions than allowed by `Limits::max_steps`.
+    StepLimitExceeded,
+    /// The stack grew deeper than allowed by `Limits::max_stack_depth`.
+    StackOverflow,
+    /// The dictionary grew larger than allowed by `Limits::max_dictionary_size`.
+    DictionaryFull,
+    /// A definition is missing its closing `;`.
+    UnterminatedDefinition,
+    /// A serialized snapshot is malformed.
+    InvalidSnapshot,
+    /// `R>` or `R@` found no value moved to the return stack by the
+    /// word being executed.
+    ReturnStackUnderflow,
+    /// Calls nested deeper than allowed by `Limits::max_return_stack_depth`.
+    ReturnStackOverflow,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Error::DivisionByZero => "division by zero",
+            Error::StackUnderflow => "stack underflow",
+            Error::UnknownWord => "unknown word",
+            Error::InvalidWord => "invalid word",
+            Error::UnbalancedControl => "unbalanced control structure",
+            Error::MisplacedControl => "control word used outside of its structure",
+            Error::Overflow => "arithmetic overflow",
+            Error::InvalidAddress => "invalid memory address",
+            Error::UnterminatedString => "unterminated string",
+            Error::Output => "failed to write output",
+            Error::StepLimitExceeded => "step limit exceeded",
+            Error::StackOverflow => "stack overflow",
+            Error::DictionaryFull => "dictionary is full",
+            Error::UnterminatedDefinition => "unterminated definition",
+            Error::InvalidSnapshot => "invalid snapshot",
+            Error::ReturnStackUnderflow => "return stack underflow",
+            Error::ReturnStackOverflow => "return stack overflow",
+        };
+
+        f.write_str(message)
+    }
 }
 
+impl std::error::Error for Error {}
+
 impl Forth {
     pub fn new() -> Forth {
+        Forth::default()
+    }
+}
+
+impl<V: Cell> Default for Forth<V> {
+    fn default() -> Self {
         Forth {
             dictionary: vec![],
             stack: vec![],
+            loops: vec![],
+            return_stack: vec![],
+            memory: vec![],
+            natives: vec![],
+            overflow: Overflow::default(),
+            output: Output::Buffer(String::new()),
+            limits: Limits::default(),
+            steps: 0,
         }
     }
+}
 
-    pub fn stack(&self) -> &[Value] {
+impl<V: Cell> Forth<V> {
+    /// Set how arithmetic overflow is handled, failing with `Error::Overflow`
+    /// by default.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Write output of printing words to the given sink instead of
+    /// collecting it in memory.
+    pub fn with_output(mut self, sink: impl Write + Send + 'static) -> Self {
+        self.output = Output::Sink(Arc::new(Mutex::new(sink)));
+        self
+    }
+
+    /// Set resource limits, all of which are disabled by default.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Define a word implemented by the host. It follows the same rules as
+    /// user-defined words: it shadows earlier definitions of the name, is
+    /// shadowed by later ones, and words defined before it keep calling the
+    /// previous definition. Errors it returns abort evaluation as usual.
+    pub fn register_native(&mut self, name: &str, native: Native<V>) -> Result {
+        let id = self.natives.len();
+        self.natives.push(native);
+
+        let result = self.define(Word::new(name, Arc::new([Instruction::Native(id)])));
+
+        if result.is_err() {
+            self.natives.pop();
+        }
+
+        result
+    }
+
+    pub fn stack(&self) -> &[V] {
         &self.stack
     }
 
-    pub fn eval(&mut self, input: &str) -> Result {
-        let input = input.to_ascii_lowercase();
+    /// Save the dictionary, stack and data memory.
+    pub fn snapshot(&self) -> Snapshot<V> {
+        Snapshot {
+            dictionary: self.dictionary.clone(),
+            stack: self.stack.clone(),
+            memory: self.memory.clone(),
+        }
+    }
+
+    /// Replace the dictionary, stack and data memory with saved ones.
+    /// Configuration and output are left as they are.
+    pub fn restore(&mut self, snapshot: &Snapshot<V>) {
+        self.dictionary = snapshot.dictionary.clone();
+        self.stack = snapshot.stack.clone();
+        self.memory = snapshot.memory.clone();
+        self.loops.clear();
+    }
+
+    /// Output collected so far. Always empty when writing to a sink
+    /// supplied with `with_output`.
+    pub fn output(&self) -> &str {
+        match &self.output {
+            Output::Buffer(buffer) => buffer,
+            Output::Sink(_) => "",
+        }
+    }
+
+    /// Take the output collected so far, clearing the buffer.
+    pub fn take_output(&mut self) -> String {
+        match &mut self.output {
+            Output::Buffer(buffer) => std::mem::take(buffer),
+            Output::Sink(_) => String::new(),
+        }
+    }
+
+    pub fn eval(&mut self, input: &str) -> Result {
+        self.eval_with_diagnostic(input)
+            .map_err(|diagnostic| diagnostic.error)
+    }
+
+    /// Same as `eval`, but on failure reports where in the input the error
+    /// happened and which user-defined words were being executed.
+    pub fn eval_with_diagnostic(&mut self, input: &str) -> std::result::Result<(), Diagnostic> {
+        self.steps = 0;
+        self._eval(input)
+    }
+
+    /// Compile a definition following `:` and add it to the dictionary.
+    fn eval_new_definition(
+        &mut self,
+        source: &str,
+        colon: Token,
+        tokens: &mut Tokens,
+    ) -> std::result::Result<(), Diagnostic> {
+        let unterminated =
+            || Diagnostic::new(Error::UnterminatedDefinition, source, colon.span.clone());
+
+        let name = tokens.next().ok_or_else(unterminated)?;
+
+        if name.text == ":" || name.text == ";" {
+            return Err(Diagnostic::new(Error::InvalidWord, source, name.span));
+        }
+
+        // Words are resolved against the dictionary as it is now, so
+        // redefining a word later does not affect this definition.
+        let mut compiler = Compiler::new(Some(self.dictionary.len()));
+
+        loop {
+            let token = tokens.next().ok_or_else(unterminated)?;
+
+            match token.text {
+                ";" => break,
+                ":" => {
+                    return Err(Diagnostic::new(Error::MisplacedControl, source, token.span));
+                }
+                _ => compiler
+                    .compile(self, &token, tokens)
+                    .map_err(|error| Diagnostic::new(error, source, token.span))?,
+            }
+        }
+
+        let (body, spans) = compiler.finish().map_err(|error| {
+            let span = compiler.openers.last().unwrap().clone();
+            Diagnostic::new(error, source, span)
+        })?;
+
+        let tokens = spans.into_iter().map(|span| Arc::from(&source[span]));
+
+        self.define(Word::new(name.text, body).with_tokens(tokens.collect()))
+            .map_err(|error| Diagnostic::new(error, source, name.span))
+    }
+
+    /// Add a word to the dictionary.
+    fn define(&mut self, word: Word<V>) -> Result {
+        let name = &word.name;
+
+        if name == ":" || name == ";" || name.parse::<V>().is_ok() {
+            return Err(Error::InvalidWord);
+        }
+
+        if let Some(max) = self.limits.max_dictionary_size
+            && self.dictionary.len() >= max
+        {
+            return Err(Error::DictionaryFull);
+        }
+
+        self.dictionary.push(word);
+
+        Ok(())
+    }
+
+    /// Define a word pushing the address of a newly allocated cell.
+    fn define_variable(&mut self, name: &str) -> Result {
+        let address = self.allocate(1)?;
+        let result = self.define(Word::new(name, Arc::new([Instruction::Literal(address)])));
+
+        // Release the cell if the word could not be defined.
+        if result.is_err() {
+            self.memory.pop();
+        }
+
+        result
+    }
+
+    /// Evaluate the input, which must close every control structure it opens.
+    fn _eval(&mut self, input: &str) -> std::result::Result<(), Diagnostic> {
+        let mut compiler = Compiler::new(None);
+
+        self.compile_input(input, &mut compiler, true)?;
+
+        if let Some(span) = compiler.openers.last() {
+            return Err(Diagnostic::new(
+                Error::UnbalancedControl,
+                input,
+                span.clone(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the input can be evaluated as it is, rather than ending
+    /// inside a definition or a control structure. Input with other errors
+    /// counts as complete, so that evaluating it reports them. Nothing is
+    /// executed, and the interpreter is left unchanged.
+    pub fn is_complete(&self, input: &str) -> bool {
+        let mut forth = self.clone();
+        let mut compiler = Compiler::new(None);
+
+        match forth.compile_input(input, &mut compiler, false) {
+            Ok(()) => compiler.is_balanced(),
+            Err(diagnostic) => diagnostic.error != Error::UnterminatedDefinition,
+        }
+    }
+
+    /// Main evaluation loop. Compile tokens and, if `execute` is set, execute
+    /// them as soon as they form a complete piece of code, i.e. right away for
+    /// ordinary words and after the closing word for control structures.
+    /// Definitions may appear anywhere outside of control structures and are
+    /// added to the dictionary either way. Control structures still open at
+    /// the end are left in the compiler.
+    fn compile_input(
+        &mut self,
+        input: &str,
+        compiler: &mut Compiler<V>,
+        execute: bool,
+    ) -> std::result::Result<(), Diagnostic> {
+        let mut tokens = Tokens::new(input);
+
+        while let Some(token) = tokens.next() {
+            match token.text {
+                ":" if compiler.is_balanced() => {
+                    self.eval_new_definition(input, token, &mut tokens)?;
+                    continue;
+                }
+                ":" | ";" => {
+                    return Err(Diagnostic::new(Error::MisplacedControl, input, token.span));
+                }
+                _ => compiler
+                    .compile(self, &token, &mut tokens)
+                    .map_err(|error| Diagnostic::new(error, input, token.span))?,
+            }
+
+            if compiler.is_balanced() {
+                let (code, spans) = compiler.finish().unwrap();
+
+                if execute {
+                    self.execute(code).map_err(|fault| {
+                        let span = spans[fault.pc].clone();
+                        Diagnostic::from_fault(fault, input, span)
+                    })?;
+                } else {
+                    // Words created by the code would be defined by running
+                    // it; declare them so that later uses still compile.
+                    for instruction in code.iter() {
+                        if let Instruction::Variable(name) | Instruction::Constant(name) =
+                            instruction
+                        {
+                            let _ = self.define(Word::new(name, Arc::new([])));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run compiled code. Calls to user-defined words push a new frame
+    /// instead of recursing, so deeply layered definitions cost nothing extra.
+    fn execute(&mut self, code: Code<V>) -> std::result::Result<(), Fault> {
+        let loops = self.loops.len();
+        let mut frames = vec![Frame {
+            code,
+            pc: 0,
+            word: None,
+            loops,
+            returns: 0,
+        }];
+
+        let result = self.run(&mut frames);
+
+        // Loops and calls interrupted by an error are never finished.
+        self.loops.truncate(loops);
+        self.return_stack.clear();
+
+        result.map_err(|error| {
+            // The failed instruction is the last one executed, which is in
+            // the caller's frame if the error is about a frame just entered.
+            let failed = frames.iter().rev().find(|frame| frame.pc > 0).unwrap();
+
+            Fault {
+                error,
+                pc: frames[0].pc - 1,
+                call_chain: frames
+                    .iter()
+                    .filter_map(|frame| self.dictionary.get(frame.word?))
+                    .map(|word| word.name.clone())
+                    .collect(),
+                inner_token: failed
+                    .word
+                    .and_then(|id| self.dictionary.get(id))
+                    .and_then(|word| word.tokens.get(failed.pc - 1))
+                    .cloned(),
+            }
+        })
+    }
+
+    /// Interpreter loop. On error, the frames are left as they were
+    /// when the failed instruction was executed.
+    fn run(&mut self, frames: &mut Vec<Frame<V>>) -> Result {
+        while let Some(frame) = frames.last_mut() {
+            let Some(instruction) = frame.code.get(frame.pc).cloned() else {
+                // Drop loops left running by `EXIT` and values left on the return stack.
+                self.loops.truncate(frame.loops);
+                self.return_stack.truncate(frame.returns);
+                frames.pop();
+                continue;
+            };
+
+            frame.pc += 1;
+            self.steps += 1;
+
+            if self.limits.max_steps.is_some_and(|max| self.steps > max) {
+                return Err(Error::StepLimitExceeded);
+            }
+
+            match instruction {
+                Instruction::Literal(value) => self.stack.push(value),
+                Instruction::Primitive(primitive) => self.apply(primitive)?,
+                Instruction::Print(text) => self.write(&text)?,
+                Instruction::String(address, _) => {
+                    let len = self.fetch_at(address)?;
+                    let start = address.checked_add(V::ONE).ok_or(Error::InvalidAddress)?;
+
+                    self.stack.push(start);
+                    self.stack.push(len);
+                }
+                Instruction::CountedString(address, _) => self.stack.push(address),
+                Instruction::Variable(name) => self.define_variable(&name)?,
+                Instruction::Constant(name) => {
+                    let value = self.pop()?;
+                    self.define(Word::new(&name, Arc::new([Instruction::Literal(value)])))?;
+                }
+                Instruction::Words => self.words()?,
+                Instruction::See(name) => self.see(&name)?,
+                Instruction::Forget(name) => {
+                    // Words being executed must not disappear from under us.
+                    if frames.len() > 1 {
+                        return Err(Error::MisplacedControl);
+                    }
+
+                    self.forget(&name)?
+                }
+                Instruction::Call(id) => {
+                    // The word may have been forgotten since the call was compiled.
+                    let word = self.dictionary.get(id).ok_or(Error::UnknownWord)?;
+
+                    frames.push(Frame {
+                        code: Arc::clone(&word.body),
+                        pc: 0,
+                        word: Some(id),
+                        loops: self.loops.len(),
+                        returns: self.return_stack.len(),
+                    })
+                }
+                Instruction::Native(id) => {
+                    // Natives are never forgotten, but a restored snapshot may
+                    // refer to one that was not registered.
+                    let native = self.natives.get(id).ok_or(Error::UnknownWord)?;
+                    native(&mut self.stack)?
+                }
+                Instruction::Branch(target) => frame.pc = target,
+                Instruction::BranchIfZero(target) => {
+                    if self.pop()? == V::ZERO {
+                        frame.pc = target
+                    }
+                }
+                Instruction::Do => {
+                    let start = self.pop()?;
+                    let limit = self.pop()?;
+
+                    self.loops.push((start, limit));
+                }
+                Instruction::Loop(target) => {
+                    // Only a restored snapshot may hold a loop without a `DO`.
+                    let (index, limit) = self.loops.last_mut().ok_or(Error::MisplacedControl)?;
+                    *index = index.wrapping_add(V::ONE);
+
+                    if *index < *limit {
+                        frame.pc = target
+                    } else {
+                        self.loops.pop();
+                    }
+                }
+                Instruction::Exit => frame.pc = frame.code.len(),
+                Instruction::ToReturn => {
+                    let value = self.pop()?;
+                    self.return_stack.push(value);
+                }
+                Instruction::FromReturn => {
+                    if self.return_stack.len() <= frame.returns {
+                        return Err(Error::ReturnStackUnderflow);
+                    }
+
+                    let value = self.return_stack.pop().unwrap();
+                    self.stack.push(value);
+                }
+                Instruction::FetchReturn => {
+                    if self.return_stack.len() <= frame.returns {
+                        return Err(Error::ReturnStackUnderflow);
+                    }
+
+                    let value = *self.return_stack.last().unwrap();
+                    self.stack.push(value);
+                }
+            }
+
+            // Checking once per instruction is enough, as none of them
+            // pushes more than a couple of values.
+            if let Some(max) = self.limits.max_stack_depth
+                && self.stack.len() > max
+            {
+                self.stack.truncate(max);
+                return Err(Error::StackOverflow);
+            }
+
+            if let Some(max) = self.limits.max_return_stack_depth
+                && frames.len() - 1 + self.return_stack.len() > max
+            {
+                return Err(Error::ReturnStackOverflow);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply(&mut self, primitive: Primitive) -> Result {
+        match primitive {
+            Primitive::Swap => self.swap(),
+            Primitive::Dup => self.dup(),
+            Primitive::Drop => self.drop(),
+            Primitive::Over => self.over(),
+            Primitive::Add => self.add(),
+            Primitive::Sub => self.sub(),
+            Primitive::Mul => self.mul(),
+            Primitive::Div => self.div(),
+            Primitive::Mod => self.modulo(),
+            Primitive::DivMod => self.div_mod(),
+            Primitive::Negate => self.negate(),
+            Primitive::Abs => self.abs(),
+            Primitive::Eq => self.compare(|a, b| a == b),
+            Primitive::Lt => self.compare(|a, b| a < b),
+            Primitive::Gt => self.compare(|a, b| a > b),
+            Primitive::And => self.and(),
+            Primitive::Or => self.or(),
+            Primitive::Invert => self.invert(),
+            Primitive::Rot => self.rot(),
+            Primitive::Nip => self.nip(),
+            Primitive::Tuck => self.tuck(),
+            Primitive::Pick => self.pick(),
+            Primitive::Roll => self.roll(),
+            Primitive::TwoDup => self.two_dup(),
+            Primitive::TwoDrop => self.two_drop(),
+            Primitive::Dot => self.dot(),
+            Primitive::DotS => self.dot_s(),
+            Primitive::Emit => self.emit(),
+            Primitive::Cr => self.write("\n"),
+            Primitive::Type => self.type_string(),
+            Primitive::Fetch => self.fetch(),
+            Primitive::Store => self.store(),
+            Primitive::Allot => self.allot(),
+            Primitive::Comma => self.comma(),
+            Primitive::Here => self.here(),
+            Primitive::Count => self.count(),
+            Primitive::I => self.loop_index(1),
+            Primitive::J => self.loop_index(2),
+        }
+    }
+
+    /// Find a word in the dictionary by its name. Returns the id of
+    /// the latest defined word, if it exists.
+    fn find_word(&self, name: &str) -> Option<usize> {
+        self.dictionary.iter().rposition(|word| word.name == name)
+    }
+
+    /// Apply an arithmetic operation according to the overflow policy.
+    fn arithmetic(
+        &self,
+        a: V,
+        b: V,
+        checked: fn(V, V) -> Option<V>,
+        wrapping: fn(V, V) -> V,
+    ) -> std::result::Result<V, Error> {
+        match self.overflow {
+            Overflow::Error => checked(a, b).ok_or(Error::Overflow),
+            Overflow::Wrap => Ok(wrapping(a, b)),
+        }
+    }
+
+    fn pop(&mut self) -> std::result::Result<V, Error> {
+        self.stack.pop().ok_or(Error::StackUnderflow)
+    }
+
+    /// Push the index of the `depth`-th innermost running loop.
+    fn loop_index(&mut self, depth: usize) -> Result {
+        let loops_len = self.loops.len();
+
+        if loops_len < depth {
+            return Err(Error::MisplacedControl);
+        }
+
+        self.stack.push(self.loops[loops_len - depth].0);
+
+        Ok(())
+    }
+
+    fn swap(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        self.stack.swap(stack_len - 1, stack_len - 2);
+
+        Ok(())
+    }
+
+    fn dup(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 1 {
+            return Err(Error::StackUnderflow);
+        };
+
+        self.stack.push(self.stack[stack_len - 1]);
+
+        Ok(())
+    }
+
+    fn drop(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 1 {
+            return Err(Error::StackUnderflow);
+        };
+
+        self.stack.remove(stack_len - 1);
+
+        Ok(())
+    }
+
+    fn over(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        self.stack.push(self.stack[stack_len - 2]);
+
+        Ok(())
+    }
+
+    fn add(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        let result = self.arithmetic(a, b, V::checked_add, V::wrapping_add)?;
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    fn sub(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        let result = self.arithmetic(a, b, V::checked_sub, V::wrapping_sub)?;
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    fn mul(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        let result = self.arithmetic(a, b, V::checked_mul, V::wrapping_mul)?;
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    fn div(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        if b == V::ZERO {
+            return Err(Error::DivisionByZero);
+        }
+
+        let result = self.arithmetic(a, b, V::checked_div, V::wrapping_div)?;
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    fn modulo(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        if b == V::ZERO {
+            return Err(Error::DivisionByZero);
+        }
+
+        let result = self.arithmetic(a, b, V::checked_rem, V::wrapping_rem)?;
+        self.stack.push(result);
+
+        Ok(())
+    }
+
+    /// Push both the remainder and the quotient, in that order.
+    fn div_mod(&mut self) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
 
-        if input.starts_with(":") {
-            return self.eval_new_definition(&input);
+        if b == V::ZERO {
+            return Err(Error::DivisionByZero);
         }
 
-        self._eval(&input)
-    }
+        let remainder = self.arithmetic(a, b, V::checked_rem, V::wrapping_rem)?;
+        let quotient = self.arithmetic(a, b, V::checked_div, V::wrapping_div)?;
+        self.stack.push(remainder);
+        self.stack.push(quotient);
 
-    /// Add a new word to the dictionary.
-    fn eval_new_definition(&mut self, definition: &str) -> Result {
-        // Strip `: ` prefix and ` ;` suffix.
-        let definition = &definition[2..definition.len() - 2];
+        Ok(())
+    }
 
-        if let Some((name, body)) = definition.split_once(" ") {
-            if name.parse::<i32>().is_ok() {
-                return Err(Error::InvalidWord);
-            }
+    fn negate(&mut self) -> Result {
+        let a = self.pop()?;
 
-            let body = self.insert_symbols(body);
-            let word = Word::new(self.dictionary.len(), name, &body);
-            self.dictionary.push(word)
-        }
+        let result = self.arithmetic(a, a, |a, _| a.checked_neg(), |a, _| a.wrapping_neg())?;
+        self.stack.push(result);
 
         Ok(())
     }
 
-    /// Replace usages of other user-defined word with symbols
-    /// (where symbol is $<dictionary id>).
-    fn insert_symbols(&self, body: &str) -> String {
-        body.split_whitespace()
-            .map(|token| match self.find_word(token) {
-                None => token.to_owned(),
-                Some(word) => word.symbol(),
-            })
-            .collect::<Vec<_>>()
-            .join(" ")
-    }
-
-    /// Recursively replace symbols representing other words in the input word's body
-    /// with their corresponding bodies.
-    fn expand_symbols(&self, body: &str) -> String {
-        body.split_whitespace()
-            .map(|token| {
-                if token.starts_with("$") {
-                    let word = self.find_word_by_symbol(token);
-                    self.expand_symbols(&word.body)
-                } else {
-                    token.to_owned()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(" ")
-    }
-
-    /// Replace user-defined words in input with
-    /// actual words' bodies before evaluation.
-    fn resolve_user_defined_words(&self, input: &str) -> String {
-        input
-            .split_whitespace()
-            .map(|token| match self.find_word(token) {
-                None => token.to_owned(),
-                Some(word) => self.expand_symbols(&word.body),
-            })
-            .collect::<Vec<_>>()
-            .join(" ")
-    }
-
-    /// Main evaluation loop. Resolve user-defined words into
-    /// their definitions, then execute operations.
-    fn _eval(&mut self, input: &str) -> Result {
-        let input = self.resolve_user_defined_words(input);
-
-        for token in input.split_whitespace() {
-            match token {
-                "swap" => self.swap()?,
-                "dup" => self.dup()?,
-                "drop" => self.drop()?,
-                "over" => self.over()?,
-                "+" => self.add()?,
-                "-" => self.sub()?,
-                "*" => self.mul()?,
-                "/" => self.div()?,
-                &_ => {
-                    if let Ok(num) = token.parse::<Value>() {
-                        self.stack.push(num);
-                    } else {
-                        return Err(Error::UnknownWord);
-                    }
-                }
-            }
-        }
+    fn abs(&mut self) -> Result {
+        let a = self.pop()?;
+
+        let result = self.arithmetic(a, a, |a, _| a.checked_abs(), |a, _| a.wrapping_abs())?;
+        self.stack.push(result);
 
         Ok(())
     }
 
-    /// Find a word in the dictionary by its name. Returns the latest
-    /// defined word, if it exists.
-    fn find_word(&self, name: &str) -> Option<&Word> {
-        self.dictionary.iter().rfind(|word| word.name == name)
-    }
+    /// Compare the two topmost values, pushing -1 (all bits set) if
+    /// the comparison holds and 0 otherwise.
+    fn compare(&mut self, op: fn(V, V) -> bool) -> Result {
+        let stack_len = self.stack.len();
+
+        if stack_len < 2 {
+            return Err(Error::StackUnderflow);
+        };
+
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
 
-    /// Find a word in the dictionary by symbol.
-    fn find_word_by_symbol(&self, symbol: &str) -> &Word {
-        let id = symbol.strip_prefix("$").unwrap().parse::<usize>().unwrap();
-        &self.dictionary[id]
+        self.stack.push(if op(a, b) { V::TRUE } else { V::ZERO });
+
+        Ok(())
     }
 
-    fn swap(&mut self) -> Result {
+    fn and(&mut self) -> Result {
         let stack_len = self.stack.len();
 
         if stack_len < 2 {
             return Err(Error::StackUnderflow);
         };
 
-        self.stack.swap(stack_len - 1, stack_len - 2);
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        self.stack.push(a & b);
 
         Ok(())
     }
 
-    fn dup(&mut self) -> Result {
+    fn or(&mut self) -> Result {
         let stack_len = self.stack.len();
 
-        if stack_len < 1 {
+        if stack_len < 2 {
             return Err(Error::StackUnderflow);
         };
 
-        self.stack.push(self.stack[stack_len - 1]);
+        let b = self.stack.pop().unwrap();
+        let a = self.stack.pop().unwrap();
+
+        self.stack.push(a | b);
 
         Ok(())
     }
 
-    fn drop(&mut self) -> Result {
+    fn invert(&mut self) -> Result {
+        let a = self.pop()?;
+
+        self.stack.push(!a);
+
+        Ok(())
+    }
+
+    /// Move the third value to the top: `a b c -- b c a`.
+    fn rot(&mut self) -> Result {
         let stack_len = self.stack.len();
 
-        if stack_len < 1 {
+        if stack_len < 3 {
             return Err(Error::StackUnderflow);
         };
 
-        self.stack.remove(stack_len - 1);
+        self.stack[stack_len - 3..].rotate_left(1);
 
         Ok(())
     }
 
-    fn over(&mut self) -> Result {
+    /// Drop the second value: `a b -- b`.
+    fn nip(&mut self) -> Result {
         let stack_len = self.stack.len();
 
         if stack_len < 2 {
             return Err(Error::StackUnderflow);
         };
 
-        self.stack.push(self.stack[stack_len - 2]);
+        self.stack.remove(stack_len - 2);
 
         Ok(())
     }
 
-    fn add(&mut self) -> Result {
+    /// Copy the top value below the second one: `a b -- b a b`.
+    fn tuck(&mut self) -> Result {
         let stack_len = self.stack.len();
 
         if stack_len < 2 {
             return Err(Error::StackUnderflow);
         };
 
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
+        self.stack.insert(stack_len - 2, self.stack[stack_len - 1]);
+
+        Ok(())
+    }
+
+    /// Pop `u` and copy the `u`-th value (counting from 0 at the top)
+    /// to the top.
+    fn pick(&mut self) -> Result {
+        let depth = self.pop_depth()?;
+        let stack_len = self.stack.len();
 
-        self.stack.push(a + b);
+        self.stack.push(self.stack[stack_len - 1 - depth]);
 
         Ok(())
     }
 
-    fn sub(&mut self) -> Result {
+    /// Pop `u` and move the `u`-th value (counting from 0 at the top)
+    /// to the top.
+    fn roll(&mut self) -> Result {
+        let depth = self.pop_depth()?;
+        let stack_len = self.stack.len();
+
+        self.stack[stack_len - 1 - depth..].rotate_left(1);
+
+        Ok(())
+    }
+
+    /// Pop an index into the stack for `PICK` and `ROLL`, making sure
+    /// it points at an existing value.
+    fn pop_depth(&mut self) -> std::result::Result<usize, Error> {
+        let depth = self.pop()?;
+
+        match depth.to_usize() {
+            Some(depth) if depth < self.stack.len() => Ok(depth),
+            _ => Err(Error::StackUnderflow),
+        }
+    }
+
+    fn two_dup(&mut self) -> Result {
         let stack_len = self.stack.len();
 
         if stack_len < 2 {
             return Err(Error::StackUnderflow);
         };
 
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
-
-        self.stack.push(a - b);
+        self.stack.extend_from_within(stack_len - 2..);
 
         Ok(())
     }
 
-    fn mul(&mut self) -> Result {
+    fn two_drop(&mut self) -> Result {
         let stack_len = self.stack.len();
 
         if stack_len < 2 {
             return Err(Error::StackUnderflow);
         };
 
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
+        self.stack.truncate(stack_len - 2);
+
+        Ok(())
+    }
 
-        self.stack.push(a * b);
+    fn write(&mut self, text: &str) -> Result {
+        match &mut self.output {
+            Output::Buffer(buffer) => buffer.push_str(text),
+            Output::Sink(sink) => sink
+                .lock()
+                .map_err(|_| Error::Output)?
+                .write_all(text.as_bytes())
+                .map_err(|_| Error::Output)?,
+        }
 
         Ok(())
     }
 
-    fn div(&mut self) -> Result {
-        let stack_len = self.stack.len();
+    fn dot(&mut self) -> Result {
+        let a = self.pop()?;
 
-        if stack_len < 2 {
-            return Err(Error::StackUnderflow);
+        self.write(&format!("{a} "))
+    }
+
+    /// Print the stack depth followed by the whole stack, bottom first,
+    /// without changing it.
+    fn dot_s(&mut self) -> Result {
+        let mut text = format!("<{}> ", self.stack.len());
+
+        for value in &self.stack {
+            text.push_str(&format!("{value} "));
+        }
+
+        self.write(&text)
+    }
+
+    /// Print the character with the given code point.
+    fn emit(&mut self) -> Result {
+        let a = self.pop()?;
+
+        self.write(to_char(a).encode_utf8(&mut [0; 4]))
+    }
+
+    /// Print the string with the given address and length.
+    fn type_string(&mut self) -> Result {
+        let len = self.pop()?.to_usize().ok_or(Error::InvalidAddress)?;
+        let address = self.pop()?.to_usize().ok_or(Error::InvalidAddress)?;
+
+        let text = address
+            .checked_add(len)
+            .and_then(|end| self.memory.get(address..end))
+            .ok_or(Error::InvalidAddress)?
+            .iter()
+            .map(|value| to_char(*value))
+            .collect::<String>();
+
+        self.write(&text)
+    }
+
+    /// Allocate the given number of zeroed cells, returning the address
+    /// of the first one.
+    fn allocate(&mut self, cells: usize) -> std::result::Result<V, Error> {
+        let address = V::from_usize(self.memory.len()).ok_or(Error::InvalidAddress)?;
+        let len = self
+            .memory
+            .len()
+            .checked_add(cells)
+            .filter(|len| *len <= MEMORY_SIZE)
+            .ok_or(Error::InvalidAddress)?;
+
+        self.memory.resize(len, V::ZERO);
+
+        Ok(address)
+    }
+
+    fn allot(&mut self) -> Result {
+        let cells = self.pop()?.to_usize().ok_or(Error::InvalidAddress)?;
+
+        self.allocate(cells)?;
+
+        Ok(())
+    }
+
+    /// Allocate a cell and store the topmost value in it.
+    fn comma(&mut self) -> Result {
+        let value = self.pop()?;
+
+        if self.memory.len() >= MEMORY_SIZE {
+            return Err(Error::InvalidAddress);
+        }
+
+        self.memory.push(value);
+
+        Ok(())
+    }
+
+    /// Push the address of the next cell to be allocated.
+    fn here(&mut self) -> Result {
+        let here = V::from_usize(self.memory.len()).ok_or(Error::InvalidAddress)?;
+
+        self.stack.push(here);
+
+        Ok(())
+    }
+
+    /// Store a counted string in newly allocated cells, one per character
+    /// after a cell holding the length, returning its address.
+    fn store_string(&mut self, text: &str) -> std::result::Result<V, Error> {
+        let cells = text
+            .chars()
+            .map(|c| V::from_usize(c as usize).ok_or(Error::Overflow))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let len = V::from_usize(cells.len()).ok_or(Error::Overflow)?;
+
+        let address = self.allocate(cells.len() + 1)?;
+        let start = self.memory.len() - cells.len();
+
+        self.memory[start - 1] = len;
+        self.memory[start..].copy_from_slice(&cells);
+
+        Ok(address)
+    }
+
+    /// Pop an address, making sure it points at an allocated cell.
+    fn pop_address(&mut self) -> std::result::Result<usize, Error> {
+        let address = self.pop()?;
+
+        self.address(address)
+    }
+
+    /// Make sure an address points at an allocated cell.
+    fn address(&self, address: V) -> std::result::Result<usize, Error> {
+        match address.to_usize() {
+            Some(address) if address < self.memory.len() => Ok(address),
+            _ => Err(Error::InvalidAddress),
+        }
+    }
+
+    fn fetch_at(&self, address: V) -> std::result::Result<V, Error> {
+        Ok(self.memory[self.address(address)?])
+    }
+
+    fn fetch(&mut self) -> Result {
+        let address = self.pop_address()?;
+
+        self.stack.push(self.memory[address]);
+
+        Ok(())
+    }
+
+    /// Turn the address of a counted string into the address and length
+    /// of its characters.
+    fn count(&mut self) -> Result {
+        let address = self.pop()?;
+        let len = self.fetch_at(address)?;
+
+        self.stack.push(address.wrapping_add(V::ONE));
+        self.stack.push(len);
+
+        Ok(())
+    }
+
+    fn store(&mut self) -> Result {
+        let address = self.pop_address()?;
+        let value = self.pop()?;
+
+        self.memory[address] = value;
+
+        Ok(())
+    }
+    /// Print user-defined words, latest first, followed by built-in words.
+    fn words(&mut self) -> Result {
+        let names = self
+            .dictionary
+            .iter()
+            .rev()
+            .map(|word| word.name.as_str())
+            .chain(PRIMITIVES.iter().map(|(name, _)| *name))
+            .chain(COMPILER_WORDS)
+            .collect::<Vec<_>>();
+
+        self.write(&format!("{}\n", names.join(" ")))
+    }
+
+    /// Print the definition of a word in source form.
+    fn see(&mut self, name: &str) -> Result {
+        let name = name.to_ascii_lowercase();
+
+        let text = match self.find_word(&name) {
+            Some(id) if matches!(*self.dictionary[id].body, [Instruction::Native(_)]) => {
+                format!("{name} is a native word\n")
+            }
+            Some(id) => format!(
+                ": {} {};\n",
+                name,
+                self.decompile(id, &self.dictionary[id].body)
+            ),
+            None if Primitive::from_name(&name).is_some() || COMPILER_WORDS.contains(&&*name) => {
+                format!("{name} is a built-in word\n")
+            }
+            None => return Err(Error::UnknownWord),
         };
 
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
+        self.write(&text)
+    }
 
-        if b == 0 {
-            return Err(Error::DivisionByZero);
+    /// Turn compiled code back into source text, recovering control
+    /// structures from the shape of the branches.
+    fn decompile(&self, id: usize, code: &[Instruction<V>]) -> String {
+        let mut thens = vec![0; code.len() + 1];
+        let mut begins = vec![0; code.len() + 1];
+        // The innermost `IF` jumping to each position, the only one that
+        // can own an `ELSE` right before it.
+        let mut ifs = vec![None; code.len() + 1];
+        let mut words = vec![];
+
+        for (pc, instruction) in code.iter().enumerate() {
+            match *instruction {
+                Instruction::Branch(target) if target > pc => {
+                    if let Some(count) = thens.get_mut(target) {
+                        *count += 1
+                    }
+                }
+                Instruction::Branch(target) | Instruction::BranchIfZero(target) if target <= pc => {
+                    begins[target] += 1
+                }
+                Instruction::BranchIfZero(target) => {
+                    if let Some(owner) = ifs.get_mut(target) {
+                        *owner = Some(pc)
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        for (pc, instruction) in code.iter().enumerate() {
+            words.extend(std::iter::repeat_n("then".to_owned(), thens[pc]));
+
+            words.extend(std::iter::repeat_n("begin".to_owned(), begins[pc]));
+
+            let word = match instruction {
+                Instruction::Literal(value) => value.to_string(),
+                Instruction::Primitive(primitive) => primitive.name().to_owned(),
+                Instruction::Print(text) => format!(".\" {text}\""),
+                Instruction::String(_, text) => format!("s\" {text}\""),
+                Instruction::CountedString(_, text) => format!("c\" {text}\""),
+                Instruction::Variable(name) => format!("variable {name}"),
+                Instruction::Constant(name) => format!("constant {name}"),
+                Instruction::Words => "words".to_owned(),
+                Instruction::See(name) => format!("see {name}"),
+                Instruction::Forget(name) => format!("forget {name}"),
+                Instruction::Call(callee) if *callee == id => "recurse".to_owned(),
+                Instruction::Call(id) => self
+                    .dictionary
+                    .get(*id)
+                    .map_or("<forgotten>".to_owned(), |word| word.name.clone()),
+                Instruction::Native(_) => "<native>".to_owned(),
+                Instruction::Branch(target) if *target > pc => "else".to_owned(),
+                Instruction::Branch(_) => "repeat".to_owned(),
+                Instruction::BranchIfZero(target) if *target <= pc => "until".to_owned(),
+                Instruction::BranchIfZero(target) => {
+                    match code.get(target - 1) {
+                        // A backward jump right before the target closes a loop
+                        // started before this branch, so this is its `WHILE`.
+                        Some(Instruction::Branch(start)) if *start <= pc => "while".to_owned(),
+                        // A forward jump right before the target is the `ELSE`
+                        // branch of the innermost `IF`, which gets the `THEN`.
+                        Some(Instruction::Branch(end))
+                            if *end > target - 1 && ifs[*target] == Some(pc) =>
+                        {
+                            "if".to_owned()
+                        }
+                        _ => {
+                            if let Some(count) = thens.get_mut(*target) {
+                                *count += 1
+                            }
+
+                            "if".to_owned()
+                        }
+                    }
+                }
+                Instruction::Do => "do".to_owned(),
+                Instruction::Loop(_) => "loop".to_owned(),
+                Instruction::Exit => "exit".to_owned(),
+                Instruction::ToReturn => ">r".to_owned(),
+                Instruction::FromReturn => "r>".to_owned(),
+                Instruction::FetchReturn => "r@".to_owned(),
+            };
+
+            words.push(word);
         }
 
-        self.stack.push(a / b);
+        words.extend(std::iter::repeat_n("then".to_owned(), thens[code.len()]));
+
+        words.iter().map(|word| format!("{word} ")).collect()
+    }
+
+    /// Remove the latest definition of a word along with every word
+    /// defined after it.
+    fn forget(&mut self, name: &str) -> Result {
+        let id = self
+            .find_word(&name.to_ascii_lowercase())
+            .ok_or(Error::UnknownWord)?;
+
+        self.dictionary.truncate(id);
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// The character with the given code point, or U+FFFD for values that
+/// are not valid characters.
+fn to_char<V: Cell>(value: V) -> char {
+    value
+        .to_usize()
+        .and_then(|code| u32::try_from(code).ok())
+        .and_then(char::from_u32)
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> std::result::Result<Vec<Value>, Error> {
+        let mut forth = Forth::new();
+        forth.eval(input)?;
+
+        Ok(forth.stack().to_vec())
+    }
+
+    #[test]
+    fn shareable_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Forth>();
+        assert_send_sync::<Snapshot>();
+
+        let mut prelude = Forth::new();
+        prelude.eval(": square dup * ;").unwrap();
+        let snapshot = Arc::new(prelude.snapshot());
+
+        let results = (1..4)
+            .map(|n| {
+                let snapshot = Arc::clone(&snapshot);
+
+                std::thread::spawn(move || {
+                    let mut forth = Forth::new();
+                    forth.restore(&snapshot);
+                    forth.eval(&format!("{n} square")).unwrap();
+                    forth.stack().to_vec()
+                })
+            })
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, vec![vec![1], vec![4], vec![9]]);
+    }
+
+    #[test]
+    fn colon_and_semicolon_are_not_names() {
+        assert_eq!(eval("1 constant :"), Err(Error::InvalidWord));
+        assert_eq!(eval("variable ;"), Err(Error::InvalidWord));
+        assert_eq!(eval(": mk variable ;"), Err(Error::InvalidWord));
+        assert_eq!(eval(": mk constant ; 1"), Err(Error::InvalidWord));
+        assert_eq!(eval(": mk variable x ; mk 1 x ! x @"), Ok(vec![1]));
+    }
+
+    #[test]
+    fn diagnostic_reports_token_inside_word() {
+        let mut forth = Forth::new();
+        let diagnostic = forth
+            .eval_with_diagnostic(": a 1 0 / ; : b a ; : c 0 if else b then ; 1 c")
+            .unwrap_err();
+
+        assert_eq!(diagnostic.error, Error::DivisionByZero);
+        assert_eq!(diagnostic.token, "c");
+        assert_eq!(diagnostic.call_chain, ["c", "b", "a"]);
+        assert_eq!(diagnostic.inner_token.as_deref(), Some("/"));
+        assert!(diagnostic.to_string().ends_with("= failed at: /"));
+
+        let diagnostic = forth.eval_with_diagnostic("1 0 /").unwrap_err();
+
+        assert_eq!(diagnostic.inner_token, None);
+    }
+
+    #[test]
+    fn snapshot_keeps_tokens() {
+        let mut forth = Forth::new();
+        forth.eval(": a 1 0 / ;").unwrap();
+
+        let snapshot = forth.snapshot().to_string().parse().unwrap();
+        let mut restored = Forth::new();
+        restored.restore(&snapshot);
+
+        let diagnostic = restored.eval_with_diagnostic("a").unwrap_err();
+
+        assert_eq!(diagnostic.inner_token.as_deref(), Some("/"));
+        assert_eq!(snapshot, forth.snapshot());
+    }
+
+    #[test]
+    fn see_nested_loops() {
+        let mut forth = Forth::new();
+        forth
+            .eval(": f begin begin 1 until 0 until ; see f")
+            .unwrap();
+
+        assert_eq!(forth.take_output(), ": f begin begin 1 until 0 until ;\n");
+
+        forth
+            .eval(": g begin begin dup while 1 - repeat 1 until ; see g")
+            .unwrap();
+        let source = forth.take_output();
+
+        assert_eq!(source, ": g begin begin dup while 1 - repeat 1 until ;\n");
+        assert_eq!(forth.eval(&source), Ok(()));
+    }
+
+    #[test]
+    fn see_nested_if_with_empty_else() {
+        let mut forth = Forth::new();
+
+        for source in [
+            ": w 1 if 2 if 3 else then then ;\n",
+            ": w 1 if 2 if else then then 5 ;\n",
+        ] {
+            forth.eval(&format!("{source} see w")).unwrap();
+            assert_eq!(forth.take_output(), source);
+        }
+    }
+
+    #[test]
+    fn complete_input() {
+        let mut forth = Forth::new();
+
+        assert!(forth.is_complete("1 2 +"));
+        assert!(!forth.is_complete("1 2 : two"));
+        assert!(!forth.is_complete(": two\n 2"));
+        assert!(forth.is_complete(": two\n 2 ;"));
+        assert!(!forth.is_complete("1 if 2"));
+        assert!(!forth.is_complete("begin 1\n while"));
+        assert!(forth.is_complete("1 if 2 then"));
+        assert!(forth.is_complete("1 if 2 loop"));
+        assert!(forth.is_complete("foo"));
+        assert!(!forth.is_complete("3 constant n n 0 do\n"));
+        assert!(!forth.is_complete("variable x : f x if\n"));
+        assert!(forth.is_complete("variable x x @ if 1 then"));
+        assert_eq!(forth.stack(), []);
+        assert_eq!(forth.eval("n"), Err(Error::UnknownWord));
+
+        forth.eval(": if 1 ;").unwrap();
+
+        assert!(forth.is_complete("if"));
+    }
+
+    #[test]
+    fn snapshot_rejects_jumps_outside_of_word() {
+        for jump in ["branch 99", "branch-if-zero 99", "loop 2"] {
+            let snapshot = format!("forth-snapshot 1\nstack\nmemory\nword f\n{jump}\nend\n");
+
+            assert_eq!(snapshot.parse::<Snapshot>(), Err(Error::InvalidSnapshot));
+        }
+
+        let snapshot = "forth-snapshot 1\nstack\nmemory\nword f\nbranch 1\nend\n";
+        let mut forth = Forth::new();
+        forth.restore(&snapshot.parse().unwrap());
+
+        assert_eq!(forth.eval("see f"), Ok(()));
+    }
+
+    #[test]
+    fn forget_inside_control_structure() {
+        let mut forth = Forth::new();
+        forth.eval(": a 111 ;").unwrap();
+
+        assert_eq!(
+            forth.eval("1 if forget a 5 constant z a then"),
+            Err(Error::MisplacedControl)
+        );
+        assert_eq!(
+            forth.eval(": f 1 if forget a then ;"),
+            Err(Error::MisplacedControl)
+        );
+        assert_eq!(forth.eval("forget a a"), Err(Error::UnknownWord));
+    }
+
+    #[test]
+    fn recurse_and_exit() {
+        assert_eq!(
+            eval(": fact dup 1 > if dup 1 - recurse * then ; 5 fact"),
+            Ok(vec![120])
+        );
+        assert_eq!(eval(": f 1 exit 2 ; f 3"), Ok(vec![1, 3]));
+        assert_eq!(
+            eval(": f 5 0 do i dup 2 = if exit then drop loop ; f 2 0 do i loop"),
+            Ok(vec![2, 0, 1])
+        );
+        assert_eq!(eval("recurse"), Err(Error::MisplacedControl));
+        assert_eq!(eval("1 exit"), Err(Error::MisplacedControl));
+    }
+
+    #[test]
+    fn return_stack() {
+        assert_eq!(eval(": f >r 1 r@ r> + + ; 2 f"), Ok(vec![5]));
+        assert_eq!(
+            eval(": g r@ ; : f 1 >r g r> ; f"),
+            Err(Error::ReturnStackUnderflow)
+        );
+        assert_eq!(eval("1 >r 2 r> +"), Err(Error::MisplacedControl));
+        assert_eq!(eval("r@"), Err(Error::MisplacedControl));
+    }
+
+    #[test]
+    fn if_else_then() {
+        assert_eq!(eval(": f if 1 else 2 then ; -1 f 0 f"), Ok(vec![1, 2]));
+        assert_eq!(eval("0 if 1 then -1 if 2 then"), Ok(vec![2]));
+    }
+
+    #[test]
+    fn nested_if() {
+        let input = ": f dup 0 < if drop -1 else 0 = if 0 else 1 then then ; -5 f 0 f 7 f";
+
+        assert_eq!(eval(input), Ok(vec![-1, 0, 1]));
+    }
+
+    #[test]
+    fn begin_until() {
+        assert_eq!(eval(": f begin 1 - dup 0 = until ; 3 f"), Ok(vec![0]));
+    }
+
+    #[test]
+    fn if_inside_begin_while_repeat() {
+        let input = ": f begin dup 0 > while dup 2 mod 0 = if dup then 1 - repeat drop ; 5 f";
+
+        assert_eq!(eval(input), Ok(vec![4, 2]));
+    }
+
+    #[test]
+    fn do_loop_index() {
+        assert_eq!(eval(": f 5 2 do i loop ; f"), Ok(vec![2, 3, 4]));
+        assert_eq!(eval("3 0 do i loop"), Ok(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn nested_do_loops() {
+        assert_eq!(
+            eval(": f 3 0 do 2 0 do i j loop loop ; f"),
+            Ok(vec![0, 0, 1, 0, 0, 1, 1, 1, 0, 2, 1, 2])
+        );
+    }
+
+    #[test]
+    fn loop_inside_begin_while_repeat() {
+        let input = ": f 0 begin 1 + dup 4 < while 0 over 0 do i + loop swap repeat drop ; f";
+
+        assert_eq!(eval(input), Ok(vec![0, 1, 3]));
+    }
+
+    #[test]
+    fn unbalanced_control() {
+        assert_eq!(eval(": f 1 if 2 loop ;"), Err(Error::UnbalancedControl));
+        assert_eq!(eval("1 if 2 loop"), Err(Error::UnbalancedControl));
+        assert_eq!(eval(": f begin 1 ;"), Err(Error::UnbalancedControl));
+        assert_eq!(eval("then"), Err(Error::UnbalancedControl));
+    }
+
+    #[test]
+    fn misplaced_control() {
+        assert_eq!(eval("i"), Err(Error::MisplacedControl));
+        assert_eq!(eval(": f j ; 2 0 do f loop"), Err(Error::MisplacedControl));
+        assert_eq!(eval("1 if : g 2 ; then"), Err(Error::MisplacedControl));
+        assert_eq!(
+            eval(": f 1 if : g 2 ; then ;"),
+            Err(Error::MisplacedControl)
+        );
+    }
+}
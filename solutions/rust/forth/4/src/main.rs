@@ -0,0 +1,63 @@
+use forth::Forth;
+use std::io::{self, BufRead, Write};
+use std::{env, fs, process};
+
+/// Run the given files in order, or start a REPL if there are none.
+fn main() {
+    let paths = env::args().skip(1).collect::<Vec<_>>();
+    let mut forth = Forth::new().with_output(io::stdout());
+
+    if paths.is_empty() {
+        repl(&mut forth);
+        return;
+    }
+
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap_or_else(|error| {
+            eprintln!("{path}: {error}");
+            process::exit(1);
+        });
+
+        if let Err(diagnostic) = forth.eval_with_diagnostic(&source) {
+            io::stdout().flush().ok();
+            eprintln!("{path}: {diagnostic}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Evaluate standard input line by line, reporting errors without exiting.
+/// Lines ending inside a definition or a control structure are kept until
+/// the input is complete and then evaluated together.
+fn repl(forth: &mut Forth) {
+    let mut source = String::new();
+
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap_or_else(|error| {
+            eprintln!("{error}");
+            process::exit(1);
+        });
+
+        source.push_str(&line);
+        source.push('\n');
+
+        if forth.is_complete(&source) {
+            eval(forth, &std::mem::take(&mut source));
+        }
+    }
+
+    // Report whatever was left unfinished at the end of the input.
+    if !source.is_empty() {
+        eval(forth, &source);
+    }
+}
+
+fn eval(forth: &mut Forth, source: &str) {
+    match forth.eval_with_diagnostic(source) {
+        Ok(()) => println!(" ok"),
+        Err(diagnostic) => {
+            io::stdout().flush().ok();
+            eprintln!("{diagnostic}");
+        }
+    }
+}
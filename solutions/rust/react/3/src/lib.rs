@@ -25,8 +25,8 @@ impl Node {
     }
 }
 
-/// Performs topological sort on a dependencies tree starting at the given root.
-fn topo(nodes: &HashMap<CellId, Node>, root: &Node) -> Vec<CellId> {
+/// Performs topological sort on a dependencies tree starting at the given roots.
+fn topo(nodes: &HashMap<CellId, Node>, roots: &[CellId]) -> Vec<CellId> {
     let mut ordered = VecDeque::new();
     let mut visited = HashSet::new();
     let mut in_progress = HashSet::new();
@@ -50,7 +50,10 @@ fn topo(nodes: &HashMap<CellId, Node>, root: &Node) -> Vec<CellId> {
         o.push_front(root.id);
     }
 
-    topo_step(nodes, root, &mut ordered, &mut visited, &mut in_progress);
+    for root in roots {
+        topo_step(nodes, &nodes[root], &mut ordered, &mut visited, &mut in_progress);
+    }
+
     Vec::from(ordered)
 }
 
@@ -93,21 +96,24 @@ impl<T: Copy + PartialEq + Debug> InputCell<T> {
     }
 }
 
+/// A boxed compute function, which may capture state and configuration.
+type ComputeFunc<'a, T> = Box<dyn FnMut(&[T]) -> T + 'a>;
+
 struct ComputeCell<'a, T: Copy + PartialEq + Debug> {
     id: ComputeCellId,
-    func: fn(&[T]) -> T,
+    func: ComputeFunc<'a, T>,
     callbacks: HashMap<CallbackId, Callback<'a, T>>,
     value: T,
     last_value: T,
 }
 
 impl<'a, T: Copy + PartialEq + Debug + Debug> ComputeCell<'a, T> {
-    pub fn new(func: fn(&[T]) -> T, initials: &[T]) -> Self {
+    pub fn new<F: FnMut(&[T]) -> T + 'a>(mut func: F, initials: &[T]) -> Self {
         let value = func(initials);
 
         Self {
             id: ComputeCellId(Uuid::new_v4()),
-            func,
+            func: Box::new(func),
             value,
             last_value: value,
             callbacks: HashMap::new(),
@@ -155,7 +161,8 @@ impl<'a, T: Copy + PartialEq + Debug> Callback<'a, T> {
 pub struct Reactor<'a, T: Copy + PartialEq + Debug> {
     nodes: HashMap<CellId, Node>,
     inputs: HashMap<InputCellId, InputCell<T>>,
-    computes: HashMap<ComputeCellId, ComputeCell<'a, T>>
+    computes: HashMap<ComputeCellId, ComputeCell<'a, T>>,
+    batched: Option<Vec<CellId>>,
 }
 
 // You are guaranteed that Reactor will only be tested against types that are Copy + PartialEq.
@@ -164,7 +171,8 @@ impl<'a, T: Copy + PartialEq + Debug> Reactor<'a, T> {
         Self {
             nodes: HashMap::new(),
             inputs: HashMap::new(),
-            computes: HashMap::new()
+            computes: HashMap::new(),
+            batched: None,
         }
     }
 
@@ -194,10 +202,23 @@ impl<'a, T: Copy + PartialEq + Debug> Reactor<'a, T> {
     // Notice that there is no way to *remove* a cell.
     // This means that you may assume, without checking, that if the dependencies exist at creation
     // time they will continue to exist as long as the Reactor exists.
-    pub fn create_compute(
+    pub fn create_compute<F: Fn(&[T]) -> T + 'a>(
+        &mut self,
+        dependencies: &[CellId],
+        compute_func: F,
+    ) -> Result<ComputeCellId, CellId> {
+        self.create_compute_mut(dependencies, compute_func)
+    }
+
+    // Creates a compute cell like `create_compute`, but with a compute function that may keep
+    // state between calls, e.g. a running total.
+    //
+    // The function is called once on creation and then once for each propagation that reaches
+    // the cell, whether or not its value changes.
+    pub fn create_compute_mut<F: FnMut(&[T]) -> T + 'a>(
         &mut self,
         dependencies: &[CellId],
-        compute_func: fn(&[T]) -> T,
+        compute_func: F,
     ) -> Result<ComputeCellId, CellId> {
         let mut initials = vec![];
 
@@ -261,33 +282,74 @@ impl<'a, T: Copy + PartialEq + Debug> Reactor<'a, T> {
     pub fn set_value(&mut self, id: InputCellId, new_value: T) -> bool {
         if let Some(cell) = self.inputs.get_mut(&id) {
             cell.set_value(new_value);
-            
-            let topo_order = topo(&self.nodes, &self.nodes[&CellId::Input(cell.id)]);
-
-            for node_id in topo_order.iter() {
-                let node = &self.nodes[node_id];
-                let mut new_values = vec![];
-
-                for parent in &node.parents {
-                    let value = self.value(*parent).unwrap();
-                    new_values.push(value);
-                }
-
-                match node_id {
-                    CellId::Compute(id) => {
-                        let cell = self.computes.get_mut(id).unwrap();
-                        cell.update(&new_values);
-                    },
-                    CellId::Input(_) => ()
-                }
+
+            match &mut self.batched {
+                Some(batched) => batched.push(CellId::Input(id)),
+                None => self.propagate(&[CellId::Input(id)]),
             }
-            
+
             return true
         }
 
         false
     }
 
+    // Runs `updates` as a single change: inputs set inside it propagate once, after it returns,
+    // so each compute cell is recomputed at most once and its callbacks see only the final value.
+    //
+    // Compute cells keep their old values until the batch ends. Nested batches join the
+    // outermost one.
+    pub fn batch<F: FnOnce(&mut Self)>(&mut self, updates: F) {
+        if self.batched.is_some() {
+            updates(self);
+            return;
+        }
+
+        self.batched = Some(vec![]);
+        updates(self);
+
+        let roots = self.batched.take().unwrap();
+        self.propagate(&roots);
+    }
+
+    // Sets several input cells in one batch.
+    //
+    // Returns false if any of the cells does not exist; the others are still set.
+    pub fn set_values(&mut self, values: &[(InputCellId, T)]) -> bool {
+        let mut all_exist = true;
+
+        self.batch(|reactor| {
+            for (id, value) in values {
+                all_exist &= reactor.set_value(*id, *value);
+            }
+        });
+
+        all_exist
+    }
+
+    /// Recomputes every cell depending on the given roots, each once and in dependency order.
+    fn propagate(&mut self, roots: &[CellId]) {
+        let topo_order = topo(&self.nodes, roots);
+
+        for node_id in topo_order.iter() {
+            let node = &self.nodes[node_id];
+            let mut new_values = vec![];
+
+            for parent in &node.parents {
+                let value = self.value(*parent).unwrap();
+                new_values.push(value);
+            }
+
+            match node_id {
+                CellId::Compute(id) => {
+                    let cell = self.computes.get_mut(id).unwrap();
+                    cell.update(&new_values);
+                },
+                CellId::Input(_) => ()
+            }
+        }
+    }
+
     // Adds a callback to the specified compute cell.
     //
     // Returns the ID of the just-added callback, or None if the cell doesn't exist.